@@ -1,30 +1,76 @@
 use std::{
-    collections::HashSet, fmt::Debug, fs::File, hash::Hash, io::BufRead, io::BufReader,
-    iter::successors,
+    collections::HashSet, env, fmt::Debug, fs::File, hash::Hash, io::BufRead, io::BufReader,
+    iter::successors, process,
 };
 
 fn main() {
-    let file = File::open("data/day10/input").unwrap();
+    // day10_2 [--export-loop | --check | --resolved | --render [--plain]] [file]
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mode = args
+        .iter()
+        .find(|a| a.starts_with("--") && *a != "--plain")
+        .map(|a| a.as_str());
+    let path = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map_or("data/day10/input", |a| a.as_str());
+
+    let file = File::open(path).unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines().map(|l| l.unwrap()).collect::<Vec<_>>();
 
-    let grid = parse_grid(&lines);
-
-    let answer = solve(&grid);
-
-    println!("{}", answer);
-}
-
-fn solve(grid: &Grid<TerrainType>) -> usize {
-    let loop_nodes = get_loop_coords(grid);
+    let maze = match parse_maze(&lines) {
+        Ok(maze) => maze,
+        Err(e) => {
+            eprintln!("invalid maze: {:?}", e);
+            process::exit(1);
+        }
+    };
 
-    // print_set(grid, &loop_nodes);
+    match mode {
+        // --export-loop prints the loop tiles in order, one "x,y" per line
+        Some("--export-loop") => {
+            for p in get_loop_path(&maze) {
+                println!("{},{}", p.x, p.y);
+            }
+        }
+        // --check reports what S turned out to be and any pipes that don't fit
+        Some("--check") => {
+            let s = *maze.grid.try_get_pos(&maze.start).unwrap();
+            println!("S at {},{} is {}", maze.start.x, maze.start.y, t_to_c(s));
+            for issue in pipe_issues(&maze.grid) {
+                println!("{:?}", issue);
+            }
+        }
+        // --resolved prints the maze with S replaced by its pipe
+        Some("--resolved") => {
+            for y in 0..maze.grid.height() {
+                let line = (0..maze.grid.width)
+                    .map(|x| t_to_c(*maze.grid.get(x, y)))
+                    .collect::<String>();
+                println!("{}", line);
+            }
+        }
+        // --render draws the maze with box-drawing characters,
+        // add --plain to leave out the colours
+        Some("--render") => {
+            let colour = !args.iter().any(|a| a == "--plain");
+            print!("{}", render(&maze, colour));
+        }
+        _ => {
+            let answer = solve(&maze);
 
-    let insides = get_inside_nodes(&grid, &loop_nodes);
+            // the two methods should always agree
+            assert_eq!(answer, count_inside_pick(&get_loop_path(&maze)));
 
-    // println!();
+            println!("{}", answer);
+        }
+    }
+}
 
-    // print_set(grid, &insides);
+fn solve(maze: &Maze) -> usize {
+    let loop_nodes = get_loop_coords(maze);
+    let insides = get_inside_nodes(&maze.grid, &loop_nodes);
 
     insides.len()
 }
@@ -109,14 +155,32 @@ fn is_blocked(left: TerrainType, right: TerrainType, d: Direction) -> bool {
     get_exits(left).contains(&right_dir) && get_exits(right).contains(&left_dir)
 }
 
-fn get_loop_coords(g: &Grid<TerrainType>) -> HashSet<Position> {
-    let start = g.find_pos(|x| *x == TerrainType::Start).unwrap();
-    DIRECTIONS
+fn get_loop_coords(maze: &Maze) -> HashSet<Position> {
+    get_loop_path(maze).into_iter().collect::<HashSet<_>>()
+}
+
+/// The loop tiles in the order you visit them, starting from S.
+fn get_loop_path(maze: &Maze) -> Vec<Position> {
+    let start_type = *maze.grid.try_get_pos(&maze.start).unwrap();
+    get_loop_coords_inner(&maze.grid, maze.start, get_exits(start_type)[0]).unwrap()
+}
+
+/// Counts the tiles enclosed by the loop without flood filling.
+///
+/// The shoelace formula gives the area of the polygon through the centres
+/// of the loop tiles, and Pick's theorem (A = I + B/2 - 1) relates that
+/// to the number of whole tiles inside it, since every loop tile
+/// is a lattice point on the boundary.
+fn count_inside_pick(path: &[Position]) -> usize {
+    let twice_area = path
         .iter()
-        .find_map(|d| get_loop_coords_inner(&g, start, *d))
-        .unwrap()
-        .into_iter()
-        .collect::<HashSet<_>>()
+        .zip(path.iter().cycle().skip(1))
+        .map(|(a, b)| (a.x * b.y) - (b.x * a.y))
+        .sum::<i64>()
+        .abs();
+
+    let boundary = path.len() as i64;
+    ((twice_area - boundary) / 2 + 1) as usize
 }
 
 fn get_loop_coords_inner(
@@ -150,21 +214,10 @@ fn get_exit_dir(terrain_type: TerrainType, entry_dir: Direction) -> Option<Direc
         return None;
     }
 
-    let exits = get_exits(terrain_type)
+    get_exits(terrain_type)
         .iter()
         .copied()
-        .filter(|x| *x != entry_dir)
-        .collect::<Vec<_>>();
-    match exits.len() {
-        0 => None,
-        1 => Some(exits[0]),
-        // This can only be the starting square, just return some garbage,
-        // it doesn't matter to our caller
-        _ => {
-            assert_eq!(terrain_type, TerrainType::Start);
-            Some(Direction::Up)
-        }
-    }
+        .find(|x| *x != entry_dir)
 }
 
 fn get_exits(t: TerrainType) -> &'static [Direction] {
@@ -175,8 +228,8 @@ fn get_exits(t: TerrainType) -> &'static [Direction] {
         TerrainType::DownToRightPipe => &[Direction::Down, Direction::Right],
         TerrainType::UpToLeftPipe => &[Direction::Up, Direction::Left],
         TerrainType::UpToRightPipe => &[Direction::Up, Direction::Right],
-        TerrainType::Ground => &[],
-        TerrainType::Start => &DIRECTIONS,
+        // S has to be resolved to a real pipe before it leads anywhere
+        TerrainType::Ground | TerrainType::Start => &[],
     }
 }
 
@@ -192,17 +245,26 @@ enum TerrainType {
     Start,
 }
 
-fn c_to_t(c: char) -> TerrainType {
+const PIPES: [TerrainType; 6] = [
+    TerrainType::VerticalPipe,
+    TerrainType::HorizontalPipe,
+    TerrainType::UpToLeftPipe,
+    TerrainType::UpToRightPipe,
+    TerrainType::DownToLeftPipe,
+    TerrainType::DownToRightPipe,
+];
+
+fn c_to_t(c: char) -> Option<TerrainType> {
     match c {
-        '|' => TerrainType::VerticalPipe,
-        '-' => TerrainType::HorizontalPipe,
-        'L' => TerrainType::UpToRightPipe,
-        'J' => TerrainType::UpToLeftPipe,
-        '7' => TerrainType::DownToLeftPipe,
-        'F' => TerrainType::DownToRightPipe,
-        '.' => TerrainType::Ground,
-        'S' => TerrainType::Start,
-        _ => panic!("invalid terrain symbol"),
+        '|' => Some(TerrainType::VerticalPipe),
+        '-' => Some(TerrainType::HorizontalPipe),
+        'L' => Some(TerrainType::UpToRightPipe),
+        'J' => Some(TerrainType::UpToLeftPipe),
+        '7' => Some(TerrainType::DownToLeftPipe),
+        'F' => Some(TerrainType::DownToRightPipe),
+        '.' => Some(TerrainType::Ground),
+        'S' => Some(TerrainType::Start),
+        _ => None,
     }
 }
 
@@ -219,34 +281,198 @@ fn t_to_c(t: TerrainType) -> char {
     }
 }
 
-fn parse_grid(lines: &[String]) -> Grid<TerrainType> {
-    let mut g = Grid::new(lines[0].len(), lines.len(), TerrainType::Ground);
+fn t_to_box(t: TerrainType) -> char {
+    match t {
+        TerrainType::VerticalPipe => '│',
+        TerrainType::HorizontalPipe => '─',
+        TerrainType::UpToRightPipe => '└',
+        TerrainType::UpToLeftPipe => '┘',
+        TerrainType::DownToLeftPipe => '┐',
+        TerrainType::DownToRightPipe => '┌',
+        TerrainType::Ground => ' ',
+        TerrainType::Start => 'S',
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum MazeError {
+    Empty,
+    /// A row that isn't the same width as the first one.
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    UnknownSymbol(Position, char),
+    MissingStart,
+    MultipleStarts(Vec<Position>),
+    /// None of the pipes S could be closes a loop.
+    NoLoop,
+    /// More than one of the pipes S could be closes a loop.
+    AmbiguousStart(Vec<TerrainType>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum PipeIssue {
+    /// The pipe leads off the grid or into the ground.
+    Dangling(Position, Direction),
+    /// The pipe leads into another pipe that doesn't lead back.
+    Contradictory(Position, Direction),
+}
+
+#[derive(Debug)]
+struct Maze {
+    /// The grid with S replaced by the pipe it stands for.
+    grid: Grid<TerrainType>,
+    start: Position,
+}
+
+fn parse_grid(lines: &[String]) -> Result<Grid<TerrainType>, MazeError> {
+    // blank lines at the end of the file aren't part of the maze
+    let height = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(0, |i| i + 1);
+    let lines = &lines[..height];
+    if lines.is_empty() {
+        return Err(MazeError::Empty);
+    }
+
+    let width = lines[0].chars().count();
+    for (row, line) in lines.iter().enumerate() {
+        let found = line.chars().count();
+        if found != width {
+            return Err(MazeError::RaggedRow {
+                row,
+                expected: width,
+                found,
+            });
+        }
+    }
+
+    let mut g = Grid::new(width, height, TerrainType::Ground);
     for (i, line) in lines.iter().enumerate() {
         for (j, c) in line.chars().enumerate() {
-            g.set(j, i, c_to_t(c));
+            let t = c_to_t(c).ok_or(MazeError::UnknownSymbol(
+                Position {
+                    x: j as i64,
+                    y: i as i64,
+                },
+                c,
+            ))?;
+            g.set(j, i, t);
+        }
+    }
+    Ok(g)
+}
+
+/// Parses the maze and works out which pipe S is hiding.
+fn parse_maze(lines: &[String]) -> Result<Maze, MazeError> {
+    let mut grid = parse_grid(lines)?;
+
+    let starts = grid
+        .pos_iter()
+        .filter(|p| grid.try_get_pos(p) == Some(&TerrainType::Start))
+        .collect::<Vec<_>>();
+    let start = match starts[..] {
+        [] => return Err(MazeError::MissingStart),
+        [p] => p,
+        _ => return Err(MazeError::MultipleStarts(starts)),
+    };
+
+    let loops = start_candidates(&grid, start)
+        .into_iter()
+        .filter(|t| {
+            let mut trial = grid.clone();
+            trial.set(start.x as usize, start.y as usize, *t);
+            get_loop_coords_inner(&trial, start, get_exits(*t)[0]).is_some()
+        })
+        .collect::<Vec<_>>();
+
+    match loops[..] {
+        [] => Err(MazeError::NoLoop),
+        [t] => {
+            grid.set(start.x as usize, start.y as usize, t);
+            Ok(Maze { grid, start })
         }
+        _ => Err(MazeError::AmbiguousStart(loops)),
     }
-    g
 }
 
-#[allow(dead_code)]
-fn print_set(g: &Grid<TerrainType>, elems: &HashSet<Position>) {
-    for y in 0..g.height() {
-        for x in 0..g.width {
-            if elems.contains(&Position {
+/// The pipes S could be, given which of its neighbours lead back into it.
+/// Only the ones that actually close a loop are any good,
+/// but that's for the caller to check.
+fn start_candidates(g: &Grid<TerrainType>, start: Position) -> Vec<TerrainType> {
+    PIPES
+        .iter()
+        .copied()
+        .filter(|t| get_exits(*t).iter().all(|d| connects_back(g, start, *d)))
+        .collect()
+}
+
+/// Whether the tile next to p in the given direction has an exit back towards p.
+fn connects_back(g: &Grid<TerrainType>, p: Position, d: Direction) -> bool {
+    g.try_get_pos(&p.move_in_direction(d))
+        .is_some_and(|t| get_exits(*t).contains(&d.reverse()))
+}
+
+/// Every pipe exit in the grid that doesn't join up with a matching exit.
+/// Junk pipes are allowed to do this, but the loop never should.
+fn pipe_issues(g: &Grid<TerrainType>) -> Vec<PipeIssue> {
+    g.pos_iter()
+        .flat_map(|p| {
+            let t = *g.try_get_pos(&p).unwrap();
+            get_exits(t)
+                .iter()
+                .filter_map(move |d| match g.try_get_pos(&p.move_in_direction(*d)) {
+                    None | Some(TerrainType::Ground) => Some(PipeIssue::Dangling(p, *d)),
+                    Some(_) if !connects_back(g, p, *d) => Some(PipeIssue::Contradictory(p, *d)),
+                    Some(_) => None,
+                })
+        })
+        .collect()
+}
+
+/// Draws the maze with box-drawing characters.
+///
+/// The loop is bold green, ground inside it is marked I and ground
+/// outside it O. Junk pipes that aren't part of the loop are dimmed,
+/// and tinted yellow if they're inside it.
+fn render(maze: &Maze, colour: bool) -> String {
+    let loop_nodes = get_loop_coords(maze);
+    let insides = get_inside_nodes(&maze.grid, &loop_nodes);
+
+    let mut out = String::new();
+    for y in 0..maze.grid.height() {
+        for x in 0..maze.grid.width {
+            let p = Position {
                 x: x as i64,
                 y: y as i64,
-            }) {
-                print!("{}", t_to_c(*g.get(x, y)));
+            };
+            let t = *maze.grid.get(x, y);
+            let (c, style) = if loop_nodes.contains(&p) {
+                (t_to_box(t), "1;32")
+            } else if t != TerrainType::Ground {
+                let style = if insides.contains(&p) { "2;33" } else { "2" };
+                (t_to_box(t), style)
+            } else if insides.contains(&p) {
+                ('I', "1;33")
             } else {
-                print!("o");
+                ('O', "34")
+            };
+
+            if colour {
+                out.push_str(&format!("\x1b[{}m{}\x1b[0m", style, c));
+            } else {
+                out.push(c);
             }
         }
-        print!("\n");
+        out.push('\n');
     }
+    out
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Grid<T: Clone> {
     width: usize,
     vec: Vec<T>,
@@ -282,19 +508,6 @@ impl<T: Clone> Grid<T> {
         }
     }
 
-    fn to_pos(&self, vec_index: usize) -> Option<Position> {
-        if vec_index < self.vec.len() {
-            let x = vec_index % self.width;
-            let y = vec_index / self.width;
-            Some(Position {
-                x: x as i64,
-                y: y as i64,
-            })
-        } else {
-            None
-        }
-    }
-
     pub fn get(&self, x: usize, y: usize) -> &T {
         &self.vec[self.to_vec_index(x, y).unwrap()]
     }
@@ -311,16 +524,6 @@ impl<T: Clone> Grid<T> {
         let index = self.to_vec_index(x, y).unwrap();
         self.vec[index] = val;
     }
-
-    fn find_pos<F: Fn(&T) -> bool>(&self, pred: F) -> Option<Position> {
-        self.vec.iter().enumerate().find_map(|(i, e)| {
-            if pred(e) {
-                Some(self.to_pos(i).unwrap())
-            } else {
-                None
-            }
-        })
-    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
@@ -402,33 +605,57 @@ mod tests {
 
     #[test]
     fn test_is_blocked() {
-        assert!(!is_blocked(c_to_t('7'), c_to_t('F'), Direction::Up));
-        assert!(!is_blocked(c_to_t('F'), c_to_t('7'), Direction::Down));
-        assert!(!is_blocked(c_to_t('|'), c_to_t('|'), Direction::Up));
-        assert!(!is_blocked(c_to_t('|'), c_to_t('|'), Direction::Down));
-        assert!(!is_blocked(c_to_t('-'), c_to_t('-'), Direction::Left));
-        assert!(!is_blocked(c_to_t('-'), c_to_t('-'), Direction::Right));
+        assert!(!is_blocked(
+            c_to_t('7').unwrap(),
+            c_to_t('F').unwrap(),
+            Direction::Up
+        ));
+        assert!(!is_blocked(
+            c_to_t('F').unwrap(),
+            c_to_t('7').unwrap(),
+            Direction::Down
+        ));
+        assert!(!is_blocked(
+            c_to_t('|').unwrap(),
+            c_to_t('|').unwrap(),
+            Direction::Up
+        ));
+        assert!(!is_blocked(
+            c_to_t('|').unwrap(),
+            c_to_t('|').unwrap(),
+            Direction::Down
+        ));
+        assert!(!is_blocked(
+            c_to_t('-').unwrap(),
+            c_to_t('-').unwrap(),
+            Direction::Left
+        ));
+        assert!(!is_blocked(
+            c_to_t('-').unwrap(),
+            c_to_t('-').unwrap(),
+            Direction::Right
+        ));
     }
 
     #[test]
     fn test_can_move() {
         {
             let h = HashSet::<Position>::from([Position { x: 0, y: 0 }, Position { x: 1, y: 0 }]);
-            let g = parse_grid(&["7F"].iter().map(|x| x.to_string()).collect::<Vec<_>>());
+            let g = parse_grid(&["7F"].iter().map(|x| x.to_string()).collect::<Vec<_>>()).unwrap();
             assert!(can_move(&g, &h, Direction::Down, Position { x: 1, y: 0 }));
             assert!(can_move(&g, &h, Direction::Up, Position { x: 1, y: 1 }));
         }
 
         {
             let h = HashSet::<Position>::from([Position { x: 0, y: 0 }, Position { x: 1, y: 0 }]);
-            let g = parse_grid(&["F7"].iter().map(|x| x.to_string()).collect::<Vec<_>>());
+            let g = parse_grid(&["F7"].iter().map(|x| x.to_string()).collect::<Vec<_>>()).unwrap();
             assert!(!can_move(&g, &h, Direction::Down, Position { x: 1, y: 0 }));
             assert!(!can_move(&g, &h, Direction::Up, Position { x: 1, y: 1 }));
         }
 
         {
             let h = HashSet::<Position>::from([Position { x: 0, y: 0 }, Position { x: 1, y: 0 }]);
-            let g = parse_grid(&["F|"].iter().map(|x| x.to_string()).collect::<Vec<_>>());
+            let g = parse_grid(&["F|"].iter().map(|x| x.to_string()).collect::<Vec<_>>()).unwrap();
             assert!(can_move(&g, &h, Direction::Down, Position { x: 1, y: 0 }));
             assert!(can_move(&g, &h, Direction::Up, Position { x: 1, y: 1 }));
         }
@@ -446,7 +673,7 @@ mod tests {
                 Position { x: 1, y: 2 },
                 Position { x: 2, y: 2 },
             ]);
-            let g = parse_grid(&s.lines().map(|x| x.to_string()).collect::<Vec<_>>());
+            let g = parse_grid(&s.lines().map(|x| x.to_string()).collect::<Vec<_>>()).unwrap();
             assert!(!can_move(&g, &h, Direction::Down, Position { x: 2, y: 2 }));
             assert!(!can_move(&g, &h, Direction::Left, Position { x: 2, y: 2 }));
             assert!(!can_move(&g, &h, Direction::Up, Position { x: 2, y: 2 }));
@@ -473,12 +700,13 @@ L--J.L7...LJS7F-7L7.
 ....FJL-7.||.||||...
 ....L---J.LJ.LJLJ...
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let c_set = get_loop_coords(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let c_set = get_loop_coords(&maze);
 
-        let p_set = g
+        let p_set = maze
+            .grid
             .pos_iter()
-            .zip(g.vec.iter())
+            .zip(maze.grid.vec.iter())
             .filter_map(|(p, v)| (*v != TerrainType::Ground).then_some(p))
             .collect::<HashSet<_>>();
 
@@ -513,8 +741,8 @@ L--J.L7...LJS7F-7L7.
 .L--J.L--J.
 ...........
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let answer = solve(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let answer = solve(&maze);
         assert_eq!(answer, 4);
     }
 
@@ -531,8 +759,8 @@ L--J.L7...LJS7F-7L7.
 .L--JL--J.
 ..........
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let answer = solve(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let answer = solve(&maze);
         assert_eq!(answer, 4);
     }
 
@@ -550,8 +778,8 @@ L--J.L7...LJS7F-7L7.
 ....FJL-7.||.||||...
 ....L---J.LJ.LJLJ...
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let answer = solve(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let answer = solve(&maze);
         assert_eq!(answer, 8);
     }
 
@@ -569,8 +797,8 @@ L---JF-JLJ.||-FJLJJ7
 L.L7LFJ|||||FJL7||LJ
 L7JLJL-JLJLJL--JLJ.L
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let answer = solve(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let answer = solve(&maze);
         assert_eq!(answer, 10);
     }
 
@@ -582,8 +810,8 @@ L7JLJL-JLJLJL--JLJ.L
 .LJ..
 .....
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let answer = solve(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let answer = solve(&maze);
         assert_eq!(answer, 0);
     }
 
@@ -595,8 +823,8 @@ S---7
 |...|
 L---J
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let answer = solve(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let answer = solve(&maze);
         assert_eq!(answer, 6);
     }
 
@@ -609,8 +837,8 @@ F7F7
 |LJ|
 L--J
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let answer = solve(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let answer = solve(&maze);
         assert_eq!(answer, 2);
     }
 
@@ -622,8 +850,206 @@ LJ
 F7
 LJ
 ";
-        let g = parse_grid(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
-        let answer = solve(&g);
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let answer = solve(&maze);
         assert_eq!(answer, 0);
     }
+
+    #[test]
+    fn test_count_inside_pick() {
+        let inputs = [
+            "\
+.....
+.S7..
+.LJ..
+.....
+",
+            "\
+S---7
+|...|
+|...|
+L---J
+",
+            "\
+F7F7
+||S|
+|LJ|
+|LJ|
+L--J
+",
+            "\
+...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........
+",
+            "\
+FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L
+",
+        ];
+
+        for s in inputs {
+            let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+            assert_eq!(count_inside_pick(&get_loop_path(&maze)), solve(&maze));
+        }
+    }
+
+    #[test]
+    fn test_get_loop_path() {
+        let s = "\
+S---7
+|...|
+L---J
+";
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let path = get_loop_path(&maze);
+
+        assert_eq!(path.len(), 12);
+        assert_eq!(path[0], Position { x: 0, y: 0 });
+        for (a, b) in path.iter().zip(path.iter().cycle().skip(1)) {
+            assert_eq!(a.x.abs_diff(b.x) + a.y.abs_diff(b.y), 1);
+        }
+    }
+
+    #[test]
+    fn test_parse_maze_infers_start() {
+        let s = "\
+.....
+.S7..
+.LJ..
+.....
+";
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        assert_eq!(maze.start, Position { x: 1, y: 1 });
+        assert_eq!(maze.grid.get(1, 1), &TerrainType::DownToRightPipe);
+        assert_eq!(pipe_issues(&maze.grid), vec![]);
+    }
+
+    #[test]
+    fn test_parse_maze_errors() {
+        let parse = |s: &str| parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>());
+
+        assert_eq!(
+            parse("S7\nLX\n").unwrap_err(),
+            MazeError::UnknownSymbol(Position { x: 1, y: 1 }, 'X')
+        );
+        assert_eq!(parse("F7\nLJ\n").unwrap_err(), MazeError::MissingStart);
+        assert_eq!(
+            parse("S7\nLS\n").unwrap_err(),
+            MazeError::MultipleStarts(vec![Position { x: 0, y: 0 }, Position { x: 1, y: 1 }])
+        );
+        assert_eq!(parse("S-.\n").unwrap_err(), MazeError::NoLoop);
+        assert_eq!(parse("").unwrap_err(), MazeError::Empty);
+        assert_eq!(parse("\n\n").unwrap_err(), MazeError::Empty);
+        assert_eq!(
+            parse("S7\nL-J\n").unwrap_err(),
+            MazeError::RaggedRow {
+                row: 1,
+                expected: 2,
+                found: 3
+            }
+        );
+        assert_eq!(
+            parse("S7\n\nLJ\n").unwrap_err(),
+            MazeError::RaggedRow {
+                row: 1,
+                expected: 2,
+                found: 0
+            }
+        );
+        assert_eq!(parse("S7\nLJ\n\n").unwrap().start, Position { x: 0, y: 0 });
+
+        // S could close either the top left or the bottom right loop
+        let s = "\
+F7..
+LS7.
+.LJ.
+";
+        assert_eq!(
+            parse(s).unwrap_err(),
+            MazeError::AmbiguousStart(vec![
+                TerrainType::UpToLeftPipe,
+                TerrainType::DownToRightPipe
+            ])
+        );
+    }
+
+    #[test]
+    fn test_pipe_issues() {
+        let s = "\
+S7.
+LJ-
+";
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        assert_eq!(
+            pipe_issues(&maze.grid),
+            vec![
+                PipeIssue::Contradictory(Position { x: 2, y: 1 }, Direction::Left),
+                PipeIssue::Dangling(Position { x: 2, y: 1 }, Direction::Right),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let s = "\
+S---7.
+|F7.|.
+|LJ.|.
+L---J.
+";
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+
+        assert_eq!(
+            render(&maze, false),
+            "\
+┌───┐O
+│┌┐I│O
+│└┘I│O
+└───┘O
+"
+        );
+
+        let s = "\
+S7F7
+LJLJ
+";
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        let out = render(&maze, true);
+        assert!(out.starts_with("\x1b[1;32m┌\x1b[0m\x1b[1;32m┐\x1b[0m\x1b[2m┌\x1b[0m"));
+
+        // the junk inside the loop is dimmed, not marked I
+        let s = "\
+S---7
+|F7.|
+|L-.|
+L---J
+";
+        let maze = parse_maze(&s.lines().map(|s| s.to_string()).collect::<Vec<_>>()).unwrap();
+        assert_eq!(
+            render(&maze, false),
+            "\
+┌───┐
+│┌┐I│
+│└─I│
+└───┘
+"
+        );
+        let out = render(&maze, true);
+        assert!(out.contains("\x1b[2;33m┌\x1b[0m\x1b[2;33m┐\x1b[0m\x1b[1;33mI\x1b[0m"));
+    }
 }
@@ -1,121 +1,592 @@
 use std::{
-    collections::HashMap,
+    env,
     fs::{self},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 fn main() {
     let input_str = fs::read_to_string("data/day12/input").unwrap();
-    let input = parse_input(&input_str);
 
-    let answer = solve(&input);
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() || args[0] == "--unfold" {
+        // day12_2 [--unfold <copies>]
+        let copies = if args.is_empty() {
+            Some(5)
+        } else {
+            args.get(1).and_then(|n| n.parse().ok())
+        };
+        let Some(copies) = copies else {
+            eprintln!("usage: day12_2 --unfold <copies>");
+            process::exit(1);
+        };
+        let input = parse_input(&input_str, copies);
+        match solve(&input) {
+            Some(answer) => println!("{}", answer),
+            None => {
+                eprintln!("arrangement count overflowed u128");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // day12_2 --nonogram <file>  counts the solutions to a 2-D puzzle, see parse_nonogram,
+    // and prints the first few of them
+    if args[0] == "--nonogram" {
+        let Some(input) = args.get(1).and_then(|f| fs::read_to_string(f).ok()) else {
+            eprintln!("usage: day12_2 --nonogram <file>");
+            process::exit(1);
+        };
+        let puzzle = parse_nonogram(&input);
+        println!("{} solution(s)", puzzle.count_solutions());
+        for solution in puzzle.solutions(NONOGRAM_SHOWN) {
+            println!();
+            print!("{}", solution.render());
+        }
+        return;
+    }
 
-    println!("{}", answer);
+    // day12_2 <record line> <k>  prints the k-th arrangement of that record
+    // day12_2 <record line> random  prints a random arrangement
+    // day12_2 <record line> all  prints every arrangement
+    let record = Record::parse(&args[0]);
+    let Some(table) = CountTable::new(&record) else {
+        eprintln!("arrangement count overflowed u128");
+        process::exit(1);
+    };
+
+    if args.get(1).map(|a| a.as_str()) == Some("all") {
+        for a in arrangements(&record, &table) {
+            println!("{}", a);
+        }
+        return;
+    }
+
+    let index = args.get(1).and_then(|a| a.parse::<u128>().ok());
+    let arrangement = match (args.get(1).map(|a| a.as_str()), index) {
+        (Some("random"), _) => {
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos() as u64;
+            sample_arrangement(&record, &table, &mut XorShift::new(seed))
+        }
+        (_, Some(k)) => kth_arrangement(&record, &table, k),
+        _ => {
+            eprintln!("usage: day12_2 <record line> <index | random | all>");
+            process::exit(1);
+        }
+    };
+    println!(
+        "{}",
+        arrangement.unwrap_or("no such arrangement".to_string())
+    );
 }
 
+/// How many nonogram solutions to print.
+const NONOGRAM_SHOWN: usize = 10;
+
 #[derive(Debug)]
 struct Input {
-    lines: Vec<(Vec<char>, Vec<i64>)>,
+    records: Vec<Record>,
 }
 
-fn min_len(groups: &[i64]) -> usize {
-    if groups.is_empty() {
-        return 0;
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Record {
+    line: Vec<char>,
+    groups: Vec<usize>,
+}
+
+impl Record {
+    /// Parses a record as written, without unfolding it.
+    fn parse(l: &str) -> Record {
+        let parts = l.split_ascii_whitespace().collect::<Vec<_>>();
+        Record {
+            line: parts[0].chars().collect(),
+            groups: parse_clues(parts[1]),
+        }
+    }
+
+    /// Joins the given number of copies of the record,
+    /// with a '?' between each copy of the line.
+    fn unfold(&self, copies: usize) -> Record {
+        let mut line = vec![];
+        for i in 0..copies {
+            if i > 0 {
+                line.push('?');
+            }
+            line.extend(&self.line);
+        }
+        Record {
+            line,
+            groups: self.groups.repeat(copies),
+        }
     }
-    groups.iter().map(|x| *x as usize).sum::<usize>() + groups.len() - 1
 }
 
-fn count_possibilities(
-    hm: &mut HashMap<(Vec<char>, Vec<i64>), i64>,
-    line: &[char],
-    groups: &[i64],
-) -> i64 {
-    if line.len() < min_len(groups) {
+/// Comma separated group sizes, where "0" means no groups at all.
+fn parse_clues(s: &str) -> Vec<usize> {
+    s.split(',')
+        .map(|x| x.parse::<usize>().unwrap())
+        .filter(|x| *x != 0)
+        .collect()
+}
+
+fn min_len(groups: &[usize]) -> usize {
+    if groups.is_empty() {
         return 0;
     }
+    groups.iter().sum::<usize>() + groups.len() - 1
+}
 
-    if groups.len() == 0 {
-        // if any broken, but no more groups, this state is impossible
-        if line.iter().any(|c| *c == '#') {
-            return 0;
+/// ways[i][g] is the number of arrangements of line[i..]
+/// using groups[g..], filled in from the back of the line.
+/// Building one fails if any of the counts overflows a u128.
+#[derive(Debug)]
+struct CountTable {
+    num_groups: usize,
+    ways: Vec<u128>,
+    // dots_before[i] is the number of '.' in line[..i],
+    // so we can check whether a group fits in one go
+    dots_before: Vec<usize>,
+}
+
+impl CountTable {
+    fn new(record: &Record) -> Option<CountTable> {
+        let line = &record.line;
+        let groups = &record.groups;
+        let n = line.len();
+        let m = groups.len();
+
+        let mut dots_before = vec![0; n + 1];
+        for i in 0..n {
+            dots_before[i + 1] = dots_before[i] + usize::from(line[i] == '.');
         }
 
-        return 1;
+        let mut table = CountTable {
+            num_groups: m,
+            ways: vec![0; (n + 1) * (m + 1)],
+            dots_before,
+        };
+
+        for i in (0..=n).rev() {
+            // no groups left, so the rest must be able to be empty
+            let rest_empty = i == n || (line[i] != '#' && table.get(i + 1, m) == 1);
+            table.set(i, m, u128::from(rest_empty));
+
+            for g in (0..m).rev() {
+                let (place, skip) = table.branch_counts(record, i, g);
+                table.set(i, g, place.checked_add(skip)?);
+            }
+        }
+
+        Some(table)
     }
-    if line.len() == 0 {
-        return 0;
+
+    fn get(&self, i: usize, g: usize) -> u128 {
+        self.ways[(i * (self.num_groups + 1)) + g]
     }
 
-    let group_size = groups[0] as usize;
-    if line.len() < group_size {
-        return 0;
+    fn set(&mut self, i: usize, g: usize, val: u128) {
+        self.ways[(i * (self.num_groups + 1)) + g] = val;
+    }
+
+    fn total(&self) -> u128 {
+        self.get(0, 0)
+    }
+
+    /// Counts for the two ways cell i can go when group g is next:
+    /// (start group g here, leave this cell empty).
+    fn branch_counts(&self, record: &Record, i: usize, g: usize) -> (u128, u128) {
+        let line = &record.line;
+        let n = line.len();
+        let group_size = record.groups[g];
+
+        let end = i + group_size;
+        let place = if end <= n
+            && self.dots_before[end] == self.dots_before[i]
+            && (end == n || line[end] != '#')
+        {
+            // skip over the gap after the group too, unless we're at the end
+            self.get((end + 1).min(n), g + 1)
+        } else {
+            0
+        };
+
+        let skip = if i < n && line[i] != '#' {
+            self.get(i + 1, g)
+        } else {
+            0
+        };
+
+        (place, skip)
+    }
+}
+
+/// None if there are too many to count in a u128.
+fn count_possibilities(record: &Record) -> Option<u128> {
+    if record.line.len() < min_len(&record.groups) {
+        return Some(0);
     }
+    CountTable::new(record).map(|t| t.total())
+}
 
-    if line.len() == group_size {
-        if groups.len() == 1 && line.iter().all(|c| *c == '#' || *c == '?') {
-            return 1;
+/// The k-th arrangement of the record in lexicographic order,
+/// where '#' sorts before '.'.
+fn kth_arrangement(record: &Record, table: &CountTable, k: u128) -> Option<String> {
+    if k >= table.total() {
+        return None;
+    }
+
+    let n = record.line.len();
+    let mut out = String::new();
+    let mut i = 0;
+    let mut g = 0;
+    let mut k = k;
+
+    while g < record.groups.len() {
+        let group_size = record.groups[g];
+        let (place, _) = table.branch_counts(record, i, g);
+        if k < place {
+            out.push_str(&"#".repeat(group_size));
+            i += group_size;
+            if i < n {
+                out.push('.');
+                i += 1;
+            }
+            g += 1;
         } else {
-            return 0;
+            k -= place;
+            out.push('.');
+            i += 1;
         }
     }
 
-    let k = (Vec::from(line), Vec::from(groups));
-    if let Some(v) = hm.get(&k) {
-        return *v;
+    out.push_str(&".".repeat(n - i));
+    Some(out)
+}
+
+/// Lazily lists every arrangement of the record in lexicographic order.
+fn arrangements<'a>(
+    record: &'a Record,
+    table: &'a CountTable,
+) -> impl Iterator<Item = String> + 'a {
+    (0..).map_while(move |k| kth_arrangement(record, table, k))
+}
+
+/// Picks one of the arrangements uniformly at random.
+fn sample_arrangement(record: &Record, table: &CountTable, rng: &mut XorShift) -> Option<String> {
+    let count = table.total();
+    if count == 0 {
+        return None;
     }
+    kth_arrangement(record, table, rng.below(count))
+}
+
+/// A small xorshift generator. Sampling doesn't need anything better,
+/// and a fixed seed makes the tests repeatable.
+#[derive(Debug)]
+struct XorShift(u64);
 
-    let mut count = 0;
-    if line.iter().take(group_size).all(|c| *c == '#' || *c == '?')
-        && (line[group_size] == '?' || line[group_size] == '.')
-    {
-        count += count_possibilities(hm, &line[group_size + 1..], &groups[1..]);
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        // the state must never be zero
+        XorShift(seed | 1)
     }
-    if line[0] == '?' || line[0] == '.' {
-        count += count_possibilities(hm, &line[1..], groups);
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
     }
 
-    hm.insert(k, count);
-    count
+    /// A number in 0..n, all equally likely.
+    fn below(&mut self, n: u128) -> u128 {
+        // throw away draws from the incomplete block at the top,
+        // which would otherwise favour the small results
+        let limit = u128::MAX - (u128::MAX % n);
+        loop {
+            let x = (u128::from(self.next_u64()) << 64) | u128::from(self.next_u64());
+            if x < limit {
+                return x % n;
+            }
+        }
+    }
 }
 
-fn solve(input: &Input) -> i64 {
-    let mut hm = HashMap::new();
-    input
-        .lines
-        .iter()
-        .map(|l| count_possibilities(&mut hm, &l.0, &l.1))
-        // .inspect(|x| println!("{:?}", x))
-        .sum()
+/// Fills in every cell of the line that is the same
+/// in all of its arrangements, or None if there are no arrangements.
+///
+/// Only whether an arrangement exists matters here, not how many there are,
+/// so this works on booleans and can't overflow. A cell can be '#' if some
+/// placement of a group covers it with a valid prefix before and suffix after,
+/// and can be '.' if a prefix ending just before it meets a suffix starting
+/// just after it. That takes O(n * m) for n cells and m groups.
+fn solve_line(line: &[char], groups: &[usize]) -> Option<Vec<char>> {
+    let n = line.len();
+    let m = groups.len();
+
+    let mut dots_before = vec![0; n + 1];
+    for i in 0..n {
+        dots_before[i + 1] = dots_before[i] + usize::from(line[i] == '.');
+    }
+    // a group can go in line[s..s + len] if none of it is definitely empty
+    let fits = |s: usize, len: usize| s + len <= n && dots_before[s + len] == dots_before[s];
+    let can_be_gap = |i: usize| line[i] != '#';
+
+    // suffix[i][g]: line[i..] can be made from groups[g..]
+    let mut suffix = vec![vec![false; m + 1]; n + 1];
+    suffix[n][m] = true;
+    for i in (0..n).rev() {
+        for g in 0..=m {
+            let skip = can_be_gap(i) && suffix[i + 1][g];
+            let place = g < m && fits(i, groups[g]) && {
+                let end = i + groups[g];
+                if end == n {
+                    g + 1 == m
+                } else {
+                    can_be_gap(end) && suffix[end + 1][g + 1]
+                }
+            };
+            suffix[i][g] = skip || place;
+        }
+    }
+    if !suffix[0][0] {
+        return None;
+    }
+
+    // prefix[i][g]: line[..i] can be made from groups[..g]
+    let mut prefix = vec![vec![false; m + 1]; n + 1];
+    prefix[0][0] = true;
+    for i in 1..=n {
+        for g in 0..=m {
+            let skip = can_be_gap(i - 1) && prefix[i - 1][g];
+            let place = g > 0 && i >= groups[g - 1] && {
+                let start = i - groups[g - 1];
+                fits(start, groups[g - 1])
+                    && if start == 0 {
+                        g == 1
+                    } else {
+                        can_be_gap(start - 1) && prefix[start - 1][g - 1]
+                    }
+            };
+            prefix[i][g] = skip || place;
+        }
+    }
+
+    // mark every run of cells some group can cover, using a difference array
+    let mut cover = vec![0i64; n + 1];
+    for (g, len) in groups.iter().enumerate() {
+        for start in (0..n).filter(|s| fits(*s, *len)) {
+            let end = start + len;
+            let before = if start == 0 {
+                g == 0
+            } else {
+                can_be_gap(start - 1) && prefix[start - 1][g]
+            };
+            let after = if end == n {
+                g + 1 == m
+            } else {
+                can_be_gap(end) && suffix[end + 1][g + 1]
+            };
+            if before && after {
+                cover[start] += 1;
+                cover[end] -= 1;
+            }
+        }
+    }
+
+    let mut out = line.to_vec();
+    let mut covered = 0;
+    for i in 0..n {
+        covered += cover[i];
+        if line[i] != '?' {
+            continue;
+        }
+
+        let can_fill = covered > 0;
+        let can_empty = (0..=m).any(|g| prefix[i][g] && suffix[i + 1][g]);
+        out[i] = match (can_fill, can_empty) {
+            (true, false) => '#',
+            (false, true) => '.',
+            _ => '?',
+        };
+    }
+
+    Some(out)
+}
+
+/// A 2-D puzzle where every row and column is a spring record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Nonogram {
+    width: usize,
+    cells: Vec<char>,
+    row_clues: Vec<Vec<usize>>,
+    col_clues: Vec<Vec<usize>>,
+}
+
+impl Nonogram {
+    fn height(&self) -> usize {
+        self.row_clues.len()
+    }
+
+    fn row(&self, y: usize) -> Vec<char> {
+        self.cells[y * self.width..(y + 1) * self.width].to_vec()
+    }
+
+    fn col(&self, x: usize) -> Vec<char> {
+        (0..self.height())
+            .map(|y| self.cells[(y * self.width) + x])
+            .collect()
+    }
+
+    /// Solves rows and columns one at a time until nothing changes.
+    /// Returns false if some line has no arrangements left.
+    fn propagate(&mut self) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for y in 0..self.height() {
+                let row = self.row(y);
+                let Some(solved) = solve_line(&row, &self.row_clues[y]) else {
+                    return false;
+                };
+                if solved != row {
+                    self.cells[y * self.width..(y + 1) * self.width].copy_from_slice(&solved);
+                    changed = true;
+                }
+            }
+
+            for x in 0..self.width {
+                let col = self.col(x);
+                let Some(solved) = solve_line(&col, &self.col_clues[x]) else {
+                    return false;
+                };
+                if solved != col {
+                    for (y, c) in solved.iter().enumerate() {
+                        self.cells[(y * self.width) + x] = *c;
+                    }
+                    changed = true;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Calls found with each solution in turn until it returns false,
+    /// guessing a cell whenever propagation gets stuck.
+    fn search(&self, mut found: impl FnMut(Nonogram) -> bool) {
+        let mut stack = vec![self.clone()];
+
+        while let Some(mut puzzle) = stack.pop() {
+            if !puzzle.propagate() {
+                continue;
+            }
+
+            match puzzle.cells.iter().position(|c| *c == '?') {
+                None => {
+                    if !found(puzzle) {
+                        return;
+                    }
+                }
+                Some(i) => {
+                    // push '.' first so that '#' is tried first
+                    for guess in ['.', '#'] {
+                        let mut next = puzzle.clone();
+                        next.cells[i] = guess;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The first limit solutions.
+    fn solutions(&self, limit: usize) -> Vec<Nonogram> {
+        let mut found = vec![];
+        if limit > 0 {
+            self.search(|solution| {
+                found.push(solution);
+                found.len() < limit
+            });
+        }
+        found
+    }
+
+    /// How many solutions there are, without keeping any of them.
+    fn count_solutions(&self) -> usize {
+        let mut count = 0;
+        self.search(|_| {
+            count += 1;
+            true
+        });
+        count
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for y in 0..self.height() {
+            out.extend(self.row(y));
+            out.push('\n');
+        }
+        out
+    }
 }
 
-fn parse_line(l: &str) -> (Vec<char>, Vec<i64>) {
-    let parts = l.split_ascii_whitespace().collect::<Vec<_>>();
-    let line = parts[0].chars().collect::<Vec<_>>();
-    let groups = parts[1]
-        .split(',')
-        .map(|x| x.parse::<i64>().unwrap())
+/// The rows are given as spring records, one per line,
+/// so some cells can already be filled in.
+/// After a blank line come the column clues, one per line.
+/// A clue of 0 means the line is empty.
+///
+/// ?? 1
+/// #? 2
+///
+/// 2
+/// 1
+fn parse_nonogram(s: &str) -> Nonogram {
+    let lines = s.lines().collect::<Vec<_>>();
+    let mut parts = lines.split(|l| l.trim().is_empty());
+    let rows = parts
+        .next()
+        .unwrap()
+        .iter()
+        .map(|l| Record::parse(l))
         .collect::<Vec<_>>();
+    let col_clues = parts
+        .next()
+        .unwrap()
+        .iter()
+        .map(|l| parse_clues(l.trim()))
+        .collect::<Vec<_>>();
+
+    let width = col_clues.len();
+    assert!(rows.iter().all(|r| r.line.len() == width));
+
+    Nonogram {
+        width,
+        cells: rows.iter().flat_map(|r| r.line.clone()).collect(),
+        row_clues: rows.into_iter().map(|r| r.groups).collect(),
+        col_clues,
+    }
+}
 
-    let mut l = Vec::<char>::new();
-    l.append(&mut line.clone());
-    for _ in 0..4 {
-        l.push('?');
-        l.append(&mut line.clone());
-    }
-    (
-        l,
-        std::iter::repeat(&groups)
-            .take(5)
-            .flatten()
-            .copied()
-            .collect(),
-    )
-}
-
-fn parse_input(s: &str) -> Input {
-    let lines = s.lines().map(|l| l.to_string()).collect::<Vec<_>>();
-    let parsed_lines = lines.iter().map(|l| parse_line(l));
+/// None if the total doesn't fit in a u128.
+fn solve(input: &Input) -> Option<u128> {
+    input
+        .records
+        .iter()
+        .try_fold(0u128, |acc, r| acc.checked_add(count_possibilities(r)?))
+}
+
+fn parse_input(s: &str, copies: usize) -> Input {
     Input {
-        lines: parsed_lines.collect(),
+        records: s.lines().map(|l| Record::parse(l).unfold(copies)).collect(),
     }
 }
 
@@ -123,22 +594,6 @@ fn parse_input(s: &str) -> Input {
 mod tests {
     use super::*;
 
-    fn count_possibilities_wrap(line: &[char], groups: &[i64]) -> i64 {
-        let mut hm = HashMap::new();
-        count_possibilities(&mut hm, line, groups)
-    }
-
-    fn parse_line_old(l: &str) -> (Vec<char>, Vec<i64>) {
-        let parts = l.split_ascii_whitespace().collect::<Vec<_>>();
-        (
-            parts[0].chars().collect::<Vec<_>>(),
-            parts[1]
-                .split(',')
-                .map(|x| x.parse::<i64>().unwrap())
-                .collect::<Vec<_>>(),
-        )
-    }
-
     #[test]
     fn test_solve2() {
         let input_str = "\
@@ -149,79 +604,311 @@ mod tests {
 ????.######..#####. 1,6,5
 ?###???????? 3,2,1
 ";
-        let input = parse_input(&input_str);
+        let input = parse_input(&input_str, 5);
         let answer = solve(&input);
 
-        assert_eq!(answer, 525152);
+        assert_eq!(answer, Some(525152));
+
+        let input = parse_input(&input_str, 1);
+        assert_eq!(solve(&input), Some(21));
     }
 
     #[test]
     fn test_count() {
         let line = "???.### 1,1,3";
-        let l = parse_line_old(&line);
-
-        let result = count_possibilities_wrap(&l.0, &l.1);
-        assert_eq!(result, 1);
+        let result = count_possibilities(&Record::parse(line));
+        assert_eq!(result, Some(1));
     }
 
     #[test]
     fn test_count2() {
         let line = ".??..??...?##. 1,1,3";
-        let l = parse_line_old(&line);
-
-        let result = count_possibilities_wrap(&l.0, &l.1);
-        assert_eq!(result, 4);
+        let result = count_possibilities(&Record::parse(line));
+        assert_eq!(result, Some(4));
     }
 
     #[test]
     fn test_count3() {
         {
             let line = "### 1";
-            let l = parse_line_old(&line);
-            let result = count_possibilities_wrap(&l.0, &l.1);
-            assert_eq!(result, 0);
+            let result = count_possibilities(&Record::parse(line));
+            assert_eq!(result, Some(0));
         }
 
         {
             let line = "??? 1";
-            let l = parse_line_old(&line);
-            let result = count_possibilities_wrap(&l.0, &l.1);
-            assert_eq!(result, 3);
+            let result = count_possibilities(&Record::parse(line));
+            assert_eq!(result, Some(3));
         }
 
         {
             let line = "?###???????? 3,2,1";
-            let l = parse_line_old(&line);
-            let result = count_possibilities_wrap(&l.0, &l.1);
-            assert_eq!(result, 10);
+            let result = count_possibilities(&Record::parse(line));
+            assert_eq!(result, Some(10));
         }
 
         {
             let line = "??????? 2,1";
-            let l = parse_line_old(&line);
-            let result = count_possibilities_wrap(&l.0, &l.1);
-            assert_eq!(result, 10);
+            let result = count_possibilities(&Record::parse(line));
+            assert_eq!(result, Some(10));
         }
 
         {
             let line = "???? 1";
-            let l = parse_line_old(&line);
-            let result = count_possibilities_wrap(&l.0, &l.1);
-            assert_eq!(result, 4);
+            let result = count_possibilities(&Record::parse(line));
+            assert_eq!(result, Some(4));
         }
 
         {
             let line = ".???? 1";
-            let l = parse_line_old(&line);
-            let result = count_possibilities_wrap(&l.0, &l.1);
-            assert_eq!(result, 4);
+            let result = count_possibilities(&Record::parse(line));
+            assert_eq!(result, Some(4));
         }
 
         {
             let line = "??? 2,1";
-            let l = parse_line_old(&line);
-            let result = count_possibilities_wrap(&l.0, &l.1);
-            assert_eq!(result, 0);
+            let result = count_possibilities(&Record::parse(line));
+            assert_eq!(result, Some(0));
+        }
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let record = Record::parse("?###???????? 3,2,1");
+        let table = CountTable::new(&record).unwrap();
+        let all = arrangements(&record, &table).collect::<Vec<_>>();
+
+        assert_eq!(all.len(), 10);
+        assert_eq!(all[0], ".###.##.#...");
+        assert_eq!(all[9], ".###....##.#");
+
+        let mut sorted = all.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(all, sorted);
+
+        for a in &all {
+            let groups = a
+                .split('.')
+                .filter(|g| !g.is_empty())
+                .map(|g| g.len())
+                .collect::<Vec<_>>();
+            assert_eq!(groups, record.groups);
+        }
+    }
+
+    #[test]
+    fn test_kth_and_sample() {
+        let record = Record::parse("?#?#?#?#?#?#?#? 1,3,1,6");
+        let table = CountTable::new(&record).unwrap();
+
+        assert_eq!(
+            kth_arrangement(&record, &table, 0),
+            Some(".#.###.#.######".to_string())
+        );
+        assert_eq!(kth_arrangement(&record, &table, 1), None);
+
+        let record = Record::parse(".??..??...?##. 1,1,3").unfold(5);
+        let table = CountTable::new(&record).unwrap();
+        let count = table.total();
+        assert_eq!(count, 16384);
+        assert_eq!(kth_arrangement(&record, &table, count), None);
+
+        let mut rng = XorShift::new(12);
+        for _ in 0..20 {
+            let a = sample_arrangement(&record, &table, &mut rng).unwrap();
+            assert_eq!(a.len(), record.line.len());
+            assert!(a
+                .chars()
+                .zip(&record.line)
+                .all(|(a, c)| *c == '?' || a == *c));
+        }
+    }
+
+    #[test]
+    fn test_large_unfold() {
+        // C(88, 22), too big for an i64
+        let record = Record::parse("???? 1").unfold(22);
+        assert_eq!(count_possibilities(&record), Some(303153433901925208740));
+
+        let record = Record::parse("?.# 1,1").unfold(3);
+        assert_eq!(count_possibilities(&record), Some(1));
+    }
+
+    #[test]
+    fn test_overflow() {
+        // C(200, 50) doesn't even fit in a u128
+        let record = Record::parse("???? 1").unfold(50);
+        assert_eq!(count_possibilities(&record), None);
+        assert!(CountTable::new(&record).is_none());
+
+        let input = Input {
+            records: vec![record],
+        };
+        assert_eq!(solve(&input), None);
+    }
+
+    #[test]
+    fn test_solve_line() {
+        assert_eq!(
+            solve_line(&"??????????".chars().collect::<Vec<_>>(), &[8]),
+            Some("??######??".chars().collect())
+        );
+        assert_eq!(
+            solve_line(&"?#??".chars().collect::<Vec<_>>(), &[1, 1]),
+            Some(".#.#".chars().collect())
+        );
+        assert_eq!(solve_line(&"#.#".chars().collect::<Vec<_>>(), &[3]), None);
+    }
+
+    // The original version, which counts the arrangements
+    // with each unknown cell filled in and then emptied.
+    fn solve_line_by_counting(line: &[char], groups: &[usize]) -> Option<Vec<char>> {
+        let mut record = Record {
+            line: line.to_vec(),
+            groups: groups.to_vec(),
+        };
+        if count_possibilities(&record) == Some(0) {
+            return None;
+        }
+
+        for (i, c) in line.iter().enumerate() {
+            if *c != '?' {
+                continue;
+            }
+
+            record.line[i] = '#';
+            let can_fill = count_possibilities(&record) != Some(0);
+            record.line[i] = '.';
+            let can_empty = count_possibilities(&record) != Some(0);
+
+            record.line[i] = match (can_fill, can_empty) {
+                (true, false) => '#',
+                (false, true) => '.',
+                _ => '?',
+            };
         }
+
+        Some(record.line)
+    }
+
+    #[test]
+    fn test_solve_line_matches_counting() {
+        let mut rng = XorShift::new(7);
+        for _ in 0..2000 {
+            let len = 1 + rng.below(12) as usize;
+            let line = (0..len)
+                .map(|_| ['?', '?', '#', '.'][rng.below(4) as usize])
+                .collect::<Vec<_>>();
+            let groups = (0..rng.below(4))
+                .map(|_| 1 + rng.below(4) as usize)
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                solve_line(&line, &groups),
+                solve_line_by_counting(&line, &groups),
+                "{:?} {:?}",
+                line,
+                groups
+            );
+        }
+    }
+
+    #[test]
+    fn test_solve_line_huge_counts() {
+        // far too many arrangements to count, but easy to solve
+        let line = "?".repeat(300).chars().collect::<Vec<_>>();
+        let solved = solve_line(&line, &[1; 60]).unwrap();
+        assert!(solved.iter().all(|c| *c == '?'));
+
+        let solved = solve_line(&line, &[149, 149]).unwrap();
+        assert_eq!(solved.iter().filter(|c| **c == '#').count(), 296);
+    }
+
+    #[test]
+    fn test_nonogram() {
+        let puzzle = parse_nonogram(
+            "\
+????? 2
+????? 1,1
+????? 5
+????? 1
+????? 0
+
+1
+3
+1,2
+2
+1
+",
+        );
+        let solutions = puzzle.solutions(10);
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(
+            solutions[0].render(),
+            "\
+.##..
+.#.#.
+#####
+..#..
+.....
+"
+        );
+    }
+
+    #[test]
+    fn test_nonogram_multiple_solutions() {
+        // the two diagonals both fit
+        let input_str = "\
+?? 1
+?? 1
+
+1
+1
+";
+        let puzzle = parse_nonogram(input_str);
+        assert_eq!(puzzle.solutions(10).len(), 2);
+
+        // unless one of the cells is already known
+        let puzzle = parse_nonogram(&input_str.replacen("?? 1", "#? 1", 1));
+        assert_eq!(puzzle.solutions(10).len(), 1);
+        assert_eq!(puzzle.solutions(10)[0].render(), "#.\n.#\n");
+
+        let puzzle = parse_nonogram(&input_str.replacen("?? 1", "## 1", 1));
+        assert_eq!(puzzle.solutions(10).len(), 0);
+        assert_eq!(puzzle.count_solutions(), 0);
+    }
+
+    #[test]
+    fn test_nonogram_count_solutions() {
+        // one filled cell in each row and column, so any permutation fits
+        let input_str = "\
+???? 1
+???? 1
+???? 1
+???? 1
+
+1
+1
+1
+1
+";
+        let puzzle = parse_nonogram(input_str);
+        assert_eq!(puzzle.count_solutions(), 24);
+        assert_eq!(puzzle.solutions(10).len(), 10);
+        assert_eq!(puzzle.solutions(0).len(), 0);
+    }
+
+    #[test]
+    fn test_xorshift_below() {
+        let mut rng = XorShift::new(0);
+        let mut seen = [0; 6];
+        for _ in 0..600 {
+            seen[rng.below(6) as usize] += 1;
+        }
+        assert!(seen.iter().all(|n| *n > 50), "{:?}", seen);
+
+        assert_eq!(rng.below(1), 0);
+        assert!(rng.below(u128::MAX) < u128::MAX);
     }
 }
@@ -1,12 +1,47 @@
-use std::fs::{self};
+use std::{
+    env,
+    fs::{self},
+    process,
+};
 
 fn main() {
     let input_str = fs::read_to_string("data/day13/input").unwrap();
     let input = parse_input(&input_str);
 
-    let answer = solve(&input);
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() {
+        let answer = solve(&input);
+        println!("{}", answer);
+        return;
+    }
 
-    println!("{}", answer);
+    // day13_2 --report
+    // shows where the smudge is in each pattern and how the mirror moved
+    if args[0] == "--report" {
+        for (i, g) in input.grids.iter().enumerate() {
+            let Some(report) = smudge_report(g) else {
+                println!("{}: no smudge", i);
+                continue;
+            };
+            println!(
+                "{}: smudge at {:?}, {:?} -> {:?}",
+                i, report.smudge, report.old_axis, report.new_axis
+            );
+            println!("{}", render(g, report.new_axis, Some(report.smudge)));
+        }
+        return;
+    }
+
+    // day13_2 <smudges> [--diagonal]
+    // lists the reflection lines of each pattern with exactly that many smudges
+    let Ok(smudges) = args[0].parse::<usize>() else {
+        eprintln!("usage: day13_2 [--report | <smudges> [--diagonal]]");
+        process::exit(1);
+    };
+    let diagonals = args.iter().any(|a| a == "--diagonal");
+    for (i, g) in input.grids.iter().enumerate() {
+        println!("{}: {:?}", i, find_reflections(g, smudges, diagonals));
+    }
 }
 
 #[derive(Debug)]
@@ -14,98 +49,139 @@ struct Input {
     grids: Vec<Grid<char>>,
 }
 
-fn flip(c: char) -> char {
-    match c {
-        '.' => '#',
-        '#' => '.',
-        _ => panic!("blah"),
-    }
-}
-
 fn solve(input: &Input) -> i64 {
     input
         .grids
         .iter()
-        .map(|g| {
-            let col = get_refl_col(g, None);
-            let row = get_refl_row(g, None);
-
-            g.pos_iter()
-                .find_map(|(x, y)| {
-                    let mut new_g = g.clone();
-                    let old_val = *new_g.get(x, y);
-                    new_g.set(x, y, flip(old_val));
-
-                    let new_col = get_refl_col(&new_g, col);
-                    let new_row = get_refl_row(&new_g, row);
-
-                    let new_answer = new_col.unwrap_or(0) + new_row.map(|i| i * 100).unwrap_or(0);
-                    if new_answer == 0 {
-                        return None;
-                    }
-                    Some(new_answer)
-                })
-                .unwrap()
+        .map(|g| summarise(&find_reflections(g, 1, false)))
+        .sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    /// Mirror between column i - 1 and column i.
+    Col(usize),
+    /// Mirror between row i - 1 and row i.
+    Row(usize),
+    /// Mirror along the line from top left to bottom right.
+    MainDiagonal,
+    /// Mirror along the line from top right to bottom left.
+    AntiDiagonal,
+}
+
+/// Puzzle answer for a set of reflection lines.
+/// Diagonals aren't part of the puzzle so they don't count.
+fn summarise(axes: &[Axis]) -> i64 {
+    axes.iter()
+        .map(|a| match a {
+            Axis::Col(i) => *i as i64,
+            Axis::Row(i) => (*i as i64) * 100,
+            Axis::MainDiagonal | Axis::AntiDiagonal => 0,
         })
         .sum()
 }
 
-fn parse_input(s: &str) -> Input {
-    let lines = s.lines().map(|l| l.to_string()).collect::<Vec<_>>();
-    let grid_inputs = lines
-        .split(|l| l.is_empty())
-        .map(|ls| Grid::from_strings(ls));
+/// All the pairs of cells that should match under the reflection
+/// but don't. A perfect reflection has none,
+/// and a reflection with k smudges has k.
+fn mismatches(g: &Grid<char>, axis: Axis) -> Vec<((usize, usize), (usize, usize))> {
+    let w = g.width;
+    let h = g.height();
 
-    Input {
-        grids: grid_inputs.collect(),
-    }
+    let pairs: Vec<((usize, usize), (usize, usize))> = match axis {
+        Axis::Col(col) => (0..col.min(w - col))
+            .flat_map(|i| (0..h).map(move |y| ((col - 1 - i, y), (col + i, y))))
+            .collect(),
+        Axis::Row(row) => (0..row.min(h - row))
+            .flat_map(|i| (0..w).map(move |x| ((x, row - 1 - i), (x, row + i))))
+            .collect(),
+        Axis::MainDiagonal => (0..h)
+            .flat_map(|y| (0..y).map(move |x| ((x, y), (y, x))))
+            .collect(),
+        Axis::AntiDiagonal => (0..h)
+            .flat_map(|y| (0..w - 1 - y).map(move |x| ((x, y), (w - 1 - y, h - 1 - x))))
+            .collect(),
+    };
+
+    pairs
+        .into_iter()
+        .filter(|(a, b)| g.get(a.0, a.1) != g.get(b.0, b.1))
+        .collect()
 }
 
-fn is_refl_col(g: &Grid<char>, col: i64) -> bool {
-    if col == 0 || col == g.width as i64 {
-        return false;
+/// Every axis that reflects the pattern with exactly the given number of smudges.
+/// Diagonals are only considered for square patterns.
+fn find_reflections(g: &Grid<char>, smudges: usize, diagonals: bool) -> Vec<Axis> {
+    let mut candidates = (1..g.width)
+        .map(Axis::Col)
+        .chain((1..g.height()).map(Axis::Row))
+        .collect::<Vec<_>>();
+    if diagonals && g.width == g.height() {
+        candidates.extend([Axis::MainDiagonal, Axis::AntiDiagonal]);
     }
 
-    for i in (0..g.width).map(|i| i as i64) {
-        let left = col - 1 - i;
-        let right = col + i;
-        if left < 0 || right >= g.width as i64 {
-            return true;
-        }
-        if !(0..g.height()).all(|y| g.get(left as usize, y) == g.get(right as usize, y)) {
-            return false;
-        }
-    }
-    return true;
+    candidates
+        .into_iter()
+        .filter(|a| mismatches(g, *a).len() == smudges)
+        .collect()
 }
 
-fn is_refl_row(g: &Grid<char>, row: i64) -> bool {
-    if row == 0 || row == g.height() as i64 {
-        return false;
-    }
+#[derive(Debug, PartialEq, Eq)]
+struct SmudgeReport {
+    /// Flipping this cell (or its mirror image across new_axis)
+    /// gives the new reflection.
+    smudge: (usize, usize),
+    old_axis: Axis,
+    new_axis: Axis,
+}
 
-    for i in (0..g.height()).map(|i| i as i64) {
-        let up = row - 1 - i;
-        let down = row + i;
-        if up < 0 || down >= g.height() as i64 {
-            return true;
+fn smudge_report(g: &Grid<char>) -> Option<SmudgeReport> {
+    let old_axis = *find_reflections(g, 0, false).first()?;
+    let new_axis = *find_reflections(g, 1, false).first()?;
+    let smudge = mismatches(g, new_axis)[0].0;
+
+    Some(SmudgeReport {
+        smudge,
+        old_axis,
+        new_axis,
+    })
+}
+
+/// Draws the pattern with the mirror line between the rows or columns
+/// and the smudge (if any) highlighted in bold yellow.
+/// Diagonal mirrors run through cells, so they aren't drawn.
+fn render(g: &Grid<char>, axis: Axis, smudge: Option<(usize, usize)>) -> String {
+    let mut out = String::new();
+    for y in 0..g.height() {
+        if axis == Axis::Row(y) {
+            out.push_str(&"-".repeat(g.width));
+            out.push('\n');
         }
-        if !(0..g.width).all(|x| g.get(x, up as usize) == g.get(x, down as usize)) {
-            return false;
+        for x in 0..g.width {
+            if axis == Axis::Col(x) {
+                out.push('|');
+            }
+            let c = *g.get(x, y);
+            if smudge == Some((x, y)) {
+                out.push_str(&format!("\x1b[1;33m{}\x1b[0m", c));
+            } else {
+                out.push(c);
+            }
         }
+        out.push('\n');
     }
-    return true;
-}
-fn get_refl_col(g: &Grid<char>, ignore_col: Option<i64>) -> Option<i64> {
-    (0..g.width)
-        .find(|i| Some(*i as i64) != ignore_col && is_refl_col(g, *i as i64))
-        .map(|i| i as i64)
+    out
 }
 
-fn get_refl_row(g: &Grid<char>, ignore_row: Option<i64>) -> Option<i64> {
-    (0..g.height())
-        .find(|i| Some(*i as i64) != ignore_row && is_refl_row(g, *i as i64))
-        .map(|i| i as i64)
+fn parse_input(s: &str) -> Input {
+    let lines = s.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+    let grid_inputs = lines
+        .split(|l| l.is_empty())
+        .map(|ls| Grid::from_strings(ls));
+
+    Input {
+        grids: grid_inputs.collect(),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -136,10 +212,6 @@ impl<T: Clone> Grid<T> {
     fn get(&self, x: usize, y: usize) -> &T {
         &self.vec[self.to_vec_index(x, y).unwrap()]
     }
-    fn set(&mut self, x: usize, y: usize, val: T) {
-        let index = self.to_vec_index(x, y).unwrap();
-        self.vec[index] = val;
-    }
     fn to_vec_index(&self, x: usize, y: usize) -> Option<usize> {
         if x < self.width && y < self.height() {
             Some((y * self.width) + x)
@@ -147,16 +219,6 @@ impl<T: Clone> Grid<T> {
             None
         }
     }
-    fn pos_iter(&self) -> impl Iterator<Item = (usize, usize)> {
-        let width = self.width;
-        let height = self.height();
-        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
-    }
-}
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
-struct Position {
-    x: i64,
-    y: i64,
 }
 
 #[cfg(test)]
@@ -221,4 +283,121 @@ mod tests {
 
         assert_eq!(answer, 100);
     }
+
+    #[test]
+    fn test_find_reflections() {
+        let input_str = "\
+#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.
+
+#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#
+";
+        let input = parse_input(&input_str);
+
+        let g = &input.grids[0];
+        assert_eq!(find_reflections(g, 0, false), vec![Axis::Col(5)]);
+        assert_eq!(find_reflections(g, 1, false), vec![Axis::Row(3)]);
+
+        let g = &input.grids[1];
+        assert_eq!(find_reflections(g, 0, false), vec![Axis::Row(4)]);
+        assert_eq!(find_reflections(g, 1, false), vec![Axis::Row(1)]);
+    }
+
+    #[test]
+    fn test_find_diagonal_reflections() {
+        let input_str = "\
+#..
+.#.
+..#
+
+#.#
+#..
+###
+";
+        let input = parse_input(&input_str);
+
+        assert_eq!(
+            find_reflections(&input.grids[0], 0, true),
+            vec![Axis::MainDiagonal, Axis::AntiDiagonal]
+        );
+        assert_eq!(find_reflections(&input.grids[0], 0, false), vec![]);
+        assert_eq!(
+            find_reflections(&input.grids[1], 0, true),
+            vec![Axis::AntiDiagonal]
+        );
+        assert_eq!(
+            find_reflections(&input.grids[1], 2, true),
+            vec![Axis::Col(1), Axis::Row(2), Axis::MainDiagonal]
+        );
+    }
+
+    #[test]
+    fn test_smudge_report() {
+        let input_str = "\
+#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.
+
+#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#
+";
+        let input = parse_input(&input_str);
+
+        assert_eq!(
+            smudge_report(&input.grids[0]),
+            Some(SmudgeReport {
+                smudge: (0, 0),
+                old_axis: Axis::Col(5),
+                new_axis: Axis::Row(3),
+            })
+        );
+        assert_eq!(
+            smudge_report(&input.grids[1]),
+            Some(SmudgeReport {
+                smudge: (4, 0),
+                old_axis: Axis::Row(4),
+                new_axis: Axis::Row(1),
+            })
+        );
+
+        // no reflection at all, let alone one a smudge could move
+        let input = parse_input("#.#\n..#\n##.\n");
+        assert_eq!(smudge_report(&input.grids[0]), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let input_str = "\
+#.#
+#..
+";
+        let input = parse_input(&input_str);
+        let g = &input.grids[0];
+
+        assert_eq!(render(g, Axis::Row(1), None), "#.#\n---\n#..\n");
+        assert_eq!(
+            render(g, Axis::Col(2), Some((2, 1))),
+            "#.|#\n#.|\x1b[1;33m.\x1b[0m\n"
+        );
+    }
 }
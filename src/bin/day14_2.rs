@@ -1,15 +1,110 @@
 use std::{
+    env,
     fs::{self},
     iter::successors,
+    process,
 };
 
 fn main() {
     let input_str = fs::read_to_string("data/day14/input").unwrap();
     let input = parse_input(&input_str);
 
-    let answer = solve(&input);
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (platform, mut rocks) = match Platform::from_grid(&input.g) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("invalid platform: {:?}", e);
+            process::exit(1);
+        }
+    };
+
+    if args.is_empty() {
+        let answer = solve(&platform, &rocks);
+        println!("{}", answer);
+        return;
+    }
+
+    let options = match parse_args(&args) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(1);
+        }
+    };
+
+    for k in &options.dumps {
+        let mut dumped = rocks.clone();
+        run_program(&platform, &mut dumped, &options.program, *k);
+        println!("after {}:", k);
+        print!("{}", platform.render(&dumped));
+    }
+
+    run_program(&platform, &mut rocks, &options.program, options.iterations);
+    println!("{}", get_load(&platform, &rocks, options.edge));
+}
+
+const USAGE: &str = "usage: day14_2 <program> [iterations] [edge] [--dump <iteration>]...
+  e.g. \"day14_2 NWSE 1000000000 N\" is the same as the puzzle";
+
+/// A custom run of a tilt program.
+#[derive(Debug, PartialEq, Eq)]
+struct RunOptions {
+    program: Vec<Direction>,
+    iterations: usize,
+    /// The edge to measure the load on.
+    edge: Direction,
+    /// Iterations to print the platform after.
+    dumps: Vec<usize>,
+}
+
+fn parse_args(args: &[String]) -> Result<RunOptions, String> {
+    let mut positional = vec![];
+    let mut dumps = vec![];
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--dump" => {
+                let k = args.get(i + 1).ok_or("--dump needs an iteration")?;
+                dumps.push(parse_iterations(k)?);
+                i += 1;
+            }
+            a if a.starts_with("--") => return Err(format!("unknown option: {}", a)),
+            a => positional.push(a),
+        }
+        i += 1;
+    }
 
-    println!("{}", answer);
+    let (program, iterations, edge) = match positional[..] {
+        [p] => (p, None, None),
+        [p, n] => (p, Some(n), None),
+        [p, n, e] => (p, Some(n), Some(e)),
+        [] => return Err("missing tilt program".to_string()),
+        _ => return Err("too many arguments".to_string()),
+    };
+
+    let program = parse_program(program)?;
+    if program.is_empty() {
+        return Err("empty tilt program".to_string());
+    }
+    let edge = match edge {
+        None => Direction::Up,
+        Some(e) => match parse_program(e)?[..] {
+            [d] => d,
+            _ => return Err(format!("edge must be one of N, E, S or W: {:?}", e)),
+        },
+    };
+
+    Ok(RunOptions {
+        program,
+        iterations: iterations.map(parse_iterations).transpose()?.unwrap_or(1),
+        edge,
+        dumps,
+    })
+}
+
+fn parse_iterations(s: &str) -> Result<usize, String> {
+    s.parse()
+        .map_err(|_| format!("invalid iteration count: {:?}", s))
 }
 
 #[derive(Debug)]
@@ -17,109 +112,275 @@ struct Input {
     g: Grid<char>,
 }
 
-fn solve(input: &Input) -> i64 {
-    let mut g = input.g.clone();
-    run_all_cycles(&mut g);
-    get_all_load(&g) as i64
+fn solve(platform: &Platform, rocks: &Rocks) -> i64 {
+    let mut rocks = rocks.clone();
+    let program = parse_program("NWSE").unwrap();
+    run_program(platform, &mut rocks, &program, 1000000000);
+    get_load(platform, &rocks, Direction::Up) as i64
 }
 
-fn fall_rock_dir(g: &mut Grid<char>, p: Position, d: Direction) {
-    let fall_pos = p.move_in_direction(d);
+/// Tilt directions by compass letter, so "NWSE" is one spin cycle.
+fn parse_program(s: &str) -> Result<Vec<Direction>, String> {
+    s.chars()
+        .map(|c| match c {
+            'N' => Ok(Direction::Up),
+            'E' => Ok(Direction::Right),
+            'S' => Ok(Direction::Down),
+            'W' => Ok(Direction::Left),
+            _ => Err(format!("unknown tilt direction: {:?}", c)),
+        })
+        .collect()
+}
+
+/// Round rocks packed one bit per tile, either by row (bit x of line y)
+/// or by column (bit y of line x), whichever way the last tilt went.
+/// They're only transposed when the tilt axis changes.
+/// Everything else about the platform stays fixed,
+/// so this is all we need to clone and compare for loop detection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rocks {
+    lines: Vec<u128>,
+    by_column: bool,
+}
+
+impl Rocks {
+    /// Switches to packing by column (or by row) if it isn't already.
+    fn orient(&mut self, platform: &Platform, by_column: bool) {
+        if self.by_column != by_column {
+            self.lines = self.transposed(platform);
+            self.by_column = by_column;
+        }
+    }
+
+    fn transposed(&self, platform: &Platform) -> Vec<u128> {
+        let len = if self.by_column {
+            platform.height
+        } else {
+            platform.width
+        };
+        transpose(&self.lines, len)
+    }
 
-    let prev = g.try_get_pos(&fall_pos);
-    if let Some(prev) = prev {
-        let curr = g.get_pos(&p);
-        if *prev == '.' {
-            g.set_pos(&fall_pos, *curr);
-            g.set_pos(&p, '.');
-            fall_rock_dir(g, fall_pos, d);
+    fn rows(&self, platform: &Platform) -> Vec<u128> {
+        if self.by_column {
+            self.transposed(platform)
+        } else {
+            self.lines.clone()
+        }
+    }
+
+    fn columns(&self, platform: &Platform) -> Vec<u128> {
+        if self.by_column {
+            self.lines.clone()
+        } else {
+            self.transposed(platform)
         }
     }
 }
 
-fn shift_col(g: &mut Grid<char>, d: Direction) {
-    match d {
-        Direction::Up => {
-            for c in 0..g.width {
-                for r in 0..g.height() {
-                    if *g.get(c, r) == 'O' {
-                        fall_rock_dir(g, pos(c as i64, r as i64), d);
-                    }
-                }
-            }
+#[derive(Debug, PartialEq, Eq)]
+enum PlatformError {
+    /// Rows and columns are each packed into a u128.
+    TooBig { width: usize, height: usize },
+}
+
+/// A stretch of a row or column between cube rocks (or the edges).
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    start: u32,
+    len: u32,
+    mask: u128,
+}
+
+/// The fixed layout of the platform.
+#[derive(Debug)]
+struct Platform {
+    width: usize,
+    height: usize,
+    row_segments: Vec<Vec<Segment>>,
+    col_segments: Vec<Vec<Segment>>,
+}
+
+impl Platform {
+    fn from_grid(g: &Grid<char>) -> Result<(Platform, Rocks), PlatformError> {
+        let width = g.width;
+        let height = g.height();
+        if width > 128 || height > 128 {
+            return Err(PlatformError::TooBig { width, height });
         }
-        Direction::Down => {
-            for c in 0..g.width {
-                for r in (0..g.height()).rev() {
-                    if *g.get(c, r) == 'O' {
-                        fall_rock_dir(g, pos(c as i64, r as i64), d);
-                    }
-                }
+
+        let mut cubes = vec![0u128; height];
+        let mut rocks = vec![0u128; height];
+        for (p, c) in g.enumerate() {
+            match c {
+                '#' => cubes[p.y as usize] |= 1 << p.x,
+                'O' => rocks[p.y as usize] |= 1 << p.x,
+                _ => {}
             }
         }
-        Direction::Left => {
-            for r in 0..g.height() {
-                for c in 0..g.width {
-                    if *g.get(c, r) == 'O' {
-                        fall_rock_dir(g, pos(c as i64, r as i64), d);
-                    }
+
+        let cube_cols = transpose(&cubes, width);
+        let platform = Platform {
+            width,
+            height,
+            row_segments: cubes.iter().map(|r| find_segments(*r, width)).collect(),
+            col_segments: cube_cols
+                .iter()
+                .map(|c| find_segments(*c, height))
+                .collect(),
+        };
+
+        let rocks = Rocks {
+            lines: rocks,
+            by_column: false,
+        };
+        Ok((platform, rocks))
+    }
+
+    fn render(&self, rocks: &Rocks) -> String {
+        let mut out = String::new();
+        for (y, row) in rocks.rows(self).iter().enumerate() {
+            // anything not covered by a segment is a cube rock
+            let open = self.row_segments[y].iter().fold(0, |acc, s| acc | s.mask);
+            for x in 0..self.width {
+                if open & (1 << x) == 0 {
+                    out.push('#');
+                } else if row & (1 << x) != 0 {
+                    out.push('O');
+                } else {
+                    out.push('.');
                 }
             }
+            out.push('\n');
         }
-        Direction::Right => {
-            for r in 0..g.height() {
-                for c in (0..g.width).rev() {
-                    if *g.get(c, r) == 'O' {
-                        fall_rock_dir(g, pos(c as i64, r as i64), d);
-                    }
-                }
+        out
+    }
+
+    #[cfg(test)]
+    fn to_grid(&self, rocks: &Rocks) -> Grid<char> {
+        let lines = self
+            .render(rocks)
+            .lines()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        Grid::from_strings(&lines)
+    }
+
+    fn tilt(&self, rocks: &mut Rocks, d: Direction) {
+        let by_column = matches!(d, Direction::Up | Direction::Down);
+        rocks.orient(self, by_column);
+
+        let segments = if by_column {
+            &self.col_segments
+        } else {
+            &self.row_segments
+        };
+        let towards_low = matches!(d, Direction::Up | Direction::Left);
+        tilt_lines(&mut rocks.lines, segments, towards_low);
+    }
+}
+
+fn low_bits(n: u32) -> u128 {
+    if n >= 128 {
+        u128::MAX
+    } else {
+        (1 << n) - 1
+    }
+}
+
+fn find_segments(cubes: u128, len: usize) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut start = 0;
+    for i in 0..=len as u32 {
+        if i == len as u32 || cubes & (1 << i) != 0 {
+            if i > start {
+                segments.push(Segment {
+                    start,
+                    len: i - start,
+                    mask: low_bits(i - start) << start,
+                });
             }
+            start = i + 1;
         }
     }
+    segments
 }
 
-fn run_cycle(g: &mut Grid<char>) {
-    for d in [
-        Direction::Up,
-        Direction::Left,
-        Direction::Down,
-        Direction::Right,
-    ] {
-        shift_col(g, d);
+/// Swaps rows for columns, where each of the input lines is len bits long.
+fn transpose(lines: &[u128], len: usize) -> Vec<u128> {
+    let mut out = vec![0u128; len];
+    for (i, line) in lines.iter().enumerate() {
+        let mut bits = *line;
+        while bits != 0 {
+            let j = bits.trailing_zeros();
+            out[j as usize] |= 1 << i;
+            bits &= bits - 1;
+        }
     }
+    out
 }
 
-fn get_all_load(g: &Grid<char>) -> usize {
-    g.enumerate()
-        .filter(|(_, c)| **c == 'O')
-        .map(|(p, _)| g.height() - p.y as usize)
-        .sum()
+/// Rolls all the rocks in each segment to the low end (or the high end)
+/// of the segment. Only the number of rocks in a segment matters.
+fn tilt_lines(lines: &mut [u128], segments: &[Vec<Segment>], towards_low: bool) {
+    for (line, segs) in lines.iter_mut().zip(segments) {
+        let mut out = 0;
+        for seg in segs {
+            let count = (*line & seg.mask).count_ones();
+            out |= if towards_low {
+                low_bits(count) << seg.start
+            } else {
+                low_bits(count) << (seg.start + seg.len - count)
+            };
+        }
+        *line = out;
+    }
 }
 
-fn run_all_cycles(g: &mut Grid<char>) {
-    let num_cycles = 1000000000;
+fn run_cycle(platform: &Platform, rocks: &mut Rocks, program: &[Direction]) {
+    for d in program {
+        platform.tilt(rocks, *d);
+    }
+}
+
+/// Load on the support beams along the given edge.
+/// Each rock contributes its distance from the opposite edge.
+fn get_load(platform: &Platform, rocks: &Rocks, edge: Direction) -> usize {
+    let line_load = |lines: &[u128], weight: &dyn Fn(usize) -> usize| {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| l.count_ones() as usize * weight(i))
+            .sum()
+    };
+
+    match edge {
+        Direction::Up => line_load(&rocks.rows(platform), &|y| platform.height - y),
+        Direction::Down => line_load(&rocks.rows(platform), &|y| y + 1),
+        Direction::Left => line_load(&rocks.columns(platform), &|x| platform.width - x),
+        Direction::Right => line_load(&rocks.columns(platform), &|x| x + 1),
+    }
+}
 
-    let it = successors(Some(g.clone()), |g| {
-        let mut g2 = g.clone();
-        run_cycle(&mut g2);
-        Some(g2)
+/// Runs the tilt program the given number of times,
+/// skipping ahead once the platform starts repeating.
+fn run_program(platform: &Platform, rocks: &mut Rocks, program: &[Direction], iterations: usize) {
+    let it = successors(Some(rocks.clone()), |r| {
+        let mut r2 = r.clone();
+        run_cycle(platform, &mut r2, program);
+        Some(r2)
     });
 
     let (preamble, period) = detect_loop(&it).unwrap();
-    dbg!(preamble);
-    dbg!(period);
 
-    assert!(preamble <= num_cycles);
-    for _ in 0..preamble {
-        run_cycle(g);
-    }
+    let remaining = if iterations <= preamble {
+        iterations
+    } else {
+        preamble + ((iterations - preamble) % period)
+    };
 
-    let remaining_cycles = num_cycles - preamble;
-
-    let remainder = remaining_cycles % period;
-
-    for _ in 0..remainder {
-        run_cycle(g);
+    for _ in 0..remaining {
+        run_cycle(platform, rocks, program);
     }
 }
 
@@ -189,6 +450,30 @@ impl<T: Clone> Grid<T> {
         self.vec.len() / self.width
     }
 
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        self.vec.iter()
+    }
+
+    fn pos_iter(&self) -> impl Iterator<Item = Position> {
+        let width = self.width;
+        let height = self.height();
+        (0..height).flat_map(move |y| {
+            (0..width).map(move |x| Position {
+                x: x.try_into().unwrap(),
+                y: y.try_into().unwrap(),
+            })
+        })
+    }
+
+    fn enumerate(&self) -> impl Iterator<Item = (Position, &T)> {
+        self.pos_iter().zip(self.iter())
+    }
+}
+
+// Only the reference simulation in the tests
+// works on the grid tile by tile.
+#[cfg(test)]
+impl<T: Clone> Grid<T> {
     fn to_vec_index(&self, x: usize, y: usize) -> Option<usize> {
         if x < self.width && y < self.height() {
             Some((y * self.width) + x)
@@ -223,25 +508,6 @@ impl<T: Clone> Grid<T> {
         let index = self.pos_to_vec_index(pos).unwrap();
         self.vec[index] = val;
     }
-
-    fn iter(&self) -> impl Iterator<Item = &T> {
-        self.vec.iter()
-    }
-
-    fn pos_iter(&self) -> impl Iterator<Item = Position> {
-        let width = self.width;
-        let height = self.height();
-        (0..height).flat_map(move |y| {
-            (0..width).map(move |x| Position {
-                x: x.try_into().unwrap(),
-                y: y.try_into().unwrap(),
-            })
-        })
-    }
-
-    fn enumerate(&self) -> impl Iterator<Item = (Position, &T)> {
-        self.pos_iter().zip(self.iter())
-    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -258,6 +524,7 @@ struct Position {
     y: i64,
 }
 
+#[cfg(test)]
 impl Position {
     fn move_in_direction(&self, direction: Direction) -> Position {
         match direction {
@@ -281,6 +548,7 @@ impl Position {
     }
 }
 
+#[cfg(test)]
 fn pos(x: i64, y: i64) -> Position {
     Position { x, y }
 }
@@ -305,15 +573,131 @@ O.#..O.#.#
 
 ";
         let input = parse_input(&input_str);
-        let answer = solve(&input);
+        let (platform, rocks) = Platform::from_grid(&input.g).unwrap();
+        let answer = solve(&platform, &rocks);
 
         assert_eq!(answer, 64);
     }
 
+    #[test]
+    fn test_platform_too_big() {
+        let input = parse_input(&format!("{}\n", ".".repeat(129)));
+        assert_eq!(
+            Platform::from_grid(&input.g).unwrap_err(),
+            PlatformError::TooBig {
+                width: 129,
+                height: 1
+            }
+        );
+
+        let input = parse_input(&format!("{}\n", ".".repeat(128)));
+        assert!(Platform::from_grid(&input.g).is_ok());
+    }
+
+    // The original tile-by-tile simulation,
+    // kept as a reference for the packed version.
+    fn fall_rock_dir(g: &mut Grid<char>, p: Position, d: Direction) {
+        let fall_pos = p.move_in_direction(d);
+
+        let prev = g.try_get_pos(&fall_pos);
+        if let Some(prev) = prev {
+            let curr = g.get_pos(&p);
+            if *prev == '.' {
+                g.set_pos(&fall_pos, *curr);
+                g.set_pos(&p, '.');
+                fall_rock_dir(g, fall_pos, d);
+            }
+        }
+    }
+
+    fn shift_col(g: &mut Grid<char>, d: Direction) {
+        match d {
+            Direction::Up => {
+                for c in 0..g.width {
+                    for r in 0..g.height() {
+                        if *g.get(c, r) == 'O' {
+                            fall_rock_dir(g, pos(c as i64, r as i64), d);
+                        }
+                    }
+                }
+            }
+            Direction::Down => {
+                for c in 0..g.width {
+                    for r in (0..g.height()).rev() {
+                        if *g.get(c, r) == 'O' {
+                            fall_rock_dir(g, pos(c as i64, r as i64), d);
+                        }
+                    }
+                }
+            }
+            Direction::Left => {
+                for r in 0..g.height() {
+                    for c in 0..g.width {
+                        if *g.get(c, r) == 'O' {
+                            fall_rock_dir(g, pos(c as i64, r as i64), d);
+                        }
+                    }
+                }
+            }
+            Direction::Right => {
+                for r in 0..g.height() {
+                    for c in (0..g.width).rev() {
+                        if *g.get(c, r) == 'O' {
+                            fall_rock_dir(g, pos(c as i64, r as i64), d);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_grid_load(g: &Grid<char>) -> usize {
+        g.enumerate()
+            .filter(|(_, c)| **c == 'O')
+            .map(|(p, _)| g.height() - p.y as usize)
+            .sum()
+    }
+
     fn solve_old(input: &Input) -> i64 {
         let mut g = input.g.clone();
         shift_col(&mut g, Direction::Up);
-        get_all_load(&g) as i64
+        get_grid_load(&g) as i64
+    }
+
+    #[test]
+    fn test_packed_matches_grid() {
+        let input_str = "\
+O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....
+";
+        let input = parse_input(&input_str);
+        let (platform, mut rocks) = Platform::from_grid(&input.g).unwrap();
+        let mut g = input.g.clone();
+
+        for _ in 0..3 {
+            for d in [
+                Direction::Up,
+                Direction::Left,
+                Direction::Down,
+                Direction::Right,
+            ] {
+                shift_col(&mut g, d);
+                platform.tilt(&mut rocks, d);
+                assert_eq!(platform.to_grid(&rocks), g);
+                assert_eq!(
+                    get_load(&platform, &rocks, Direction::Up),
+                    get_grid_load(&g)
+                );
+            }
+        }
     }
 
     #[test]
@@ -371,4 +755,83 @@ O.#..O.#.#
         let answer = detect_loop(&it);
         assert_eq!(answer, Some((20, 10)));
     }
+
+    #[test]
+    fn test_run_program_skips_ahead() {
+        let input_str = "\
+O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....
+";
+        let input = parse_input(&input_str);
+
+        for program in ["NWSE", "NN", "E", "SWWN"] {
+            let program = parse_program(program).unwrap();
+            let (platform, start) = Platform::from_grid(&input.g).unwrap();
+            let mut stepped = start.clone();
+            for iterations in 0..40 {
+                let mut rocks = start.clone();
+                run_program(&platform, &mut rocks, &program, iterations);
+                assert_eq!(rocks, stepped);
+
+                run_cycle(&platform, &mut stepped, &program);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_load() {
+        let input_str = "\
+O.#
+..O
+.O.
+";
+        let input = parse_input(&input_str);
+        let (platform, rocks) = Platform::from_grid(&input.g).unwrap();
+
+        assert_eq!(get_load(&platform, &rocks, Direction::Up), 3 + 2 + 1);
+        assert_eq!(get_load(&platform, &rocks, Direction::Down), 1 + 2 + 3);
+        assert_eq!(get_load(&platform, &rocks, Direction::Left), 3 + 2 + 1);
+        assert_eq!(get_load(&platform, &rocks, Direction::Right), 1 + 2 + 3);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |s: &str| s.split(' ').map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            parse_args(&args("NWSE 1000000000 N")),
+            Ok(RunOptions {
+                program: parse_program("NWSE").unwrap(),
+                iterations: 1000000000,
+                edge: Direction::Up,
+                dumps: vec![],
+            })
+        );
+        assert_eq!(
+            parse_args(&args("E --dump 0 --dump 3")),
+            Ok(RunOptions {
+                program: vec![Direction::Right],
+                iterations: 1,
+                edge: Direction::Up,
+                dumps: vec![0, 3],
+            })
+        );
+
+        assert!(parse_args(&args("NWSE 10 ")).is_err());
+        assert!(parse_args(&args("NWSE 10 NS")).is_err());
+        assert!(parse_args(&args("NWSE 10 X")).is_err());
+        assert!(parse_args(&args("NWSE ten")).is_err());
+        assert!(parse_args(&args("NWSE --dump")).is_err());
+        assert!(parse_args(&args("NWSE --dumps 3")).is_err());
+        assert!(parse_args(&args("NXSE")).is_err());
+        assert!(parse_args(&[]).is_err());
+    }
 }
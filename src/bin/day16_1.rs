@@ -1,205 +1,113 @@
 use std::{
     collections::HashSet,
+    env,
     fs::{self},
-    hash::Hash,
 };
 
+use aoc23::day16::{follow_beams, parse_input, pos, Direction, Grid, Input, Position, DIRECTIONS};
+
 fn main() {
     let input_str = fs::read_to_string("data/day16/input").unwrap();
     let input = parse_input(&input_str);
 
     let answer = solve(&input);
     println!("{}", answer);
-}
 
-#[derive(Debug)]
-struct Input {
-    g: Grid<char>,
-}
-
-fn reflect(d: Direction, val: char) -> Direction {
-    match (d, val) {
-        (Direction::Right, '/') => Direction::Up,
-        (Direction::Left, '/') => Direction::Down,
-        (Direction::Up, '/') => Direction::Right,
-        (Direction::Down, '/') => Direction::Left,
-
-        (Direction::Right, '\\') => Direction::Down,
-        (Direction::Left, '\\') => Direction::Up,
-        (Direction::Up, '\\') => Direction::Left,
-        (Direction::Down, '\\') => Direction::Right,
-        _ => panic!("unhandled"),
-    }
-}
-
-fn succ_dirs(val: &char, d: Direction) -> Vec<Direction> {
-    match (val, d) {
-        ('.', _) => vec![d],
-        ('/', _) | ('\\', _) => vec![reflect(d, *val)],
-        ('-', d) if d == Direction::Left || d == Direction::Right => vec![d],
-        ('|', d) if d == Direction::Up || d == Direction::Down => vec![d],
-        ('-', _) => vec![Direction::Left, Direction::Right],
-        ('|', _) => vec![Direction::Up, Direction::Down],
-        _ => panic!("unhandled"),
+    // Optional visualisations of the beam:
+    //   --ansi        contraption with energised tiles highlighted
+    //   --dirs        beam directions drawn over empty tiles
+    //   --ppm <file>  image where brightness is the number of beam directions
+    let args = env::args().collect::<Vec<_>>();
+    let Some(option) = args.get(1) else {
+        return;
+    };
+    let beams = beam_map(&input, pos(0, 0), Direction::Right);
+    match option.as_str() {
+        "--ansi" => print!("{}", render_ansi(&input.g, &beams)),
+        "--dirs" => print!("{}", render_dirs(&input.g, &beams)),
+        "--ppm" => {
+            let path = args.get(2).expect("--ppm needs an output path");
+            fs::write(path, render_ppm(&beams)).unwrap();
+        }
+        a => panic!("unknown option: {}", a),
     }
 }
 
-fn succ(g: &Grid<char>, p: Position, d: Direction) -> Vec<(Position, Direction)> {
-    let val = g.get_pos(&p);
-    let next_dirs = succ_dirs(val, d);
-
-    next_dirs
-        .iter()
-        .filter_map(|next_d| {
-            let new_p = p.move_in_direction(*next_d);
-            g.try_get_pos(&new_p).is_some().then_some((new_p, *next_d))
-        })
-        .collect()
-}
-
 fn solve(input: &Input) -> i64 {
     let start = pos(0, 0);
 
-    let seen = flood_fill((start, Direction::Right), |(p, d)| succ(&input.g, *p, *d));
-    let energized = seen.iter().map(|x| x.0).collect::<HashSet<_>>();
-
-    // for r in input.g.rows() {
-    //     for c in input.g.cols() {
-    //         if energized.contains(&pos(c as i64, r as i64)) {
-    //             print!("#");
-    //         } else {
-    //             print!(".");
-    //         }
-    //     }
-    //     println!();
-    // }
+    let seen = follow_beams(input, start, Direction::Right);
+    let energized = seen.iter().map(|b| b.p).collect::<HashSet<_>>();
 
     energized.len() as i64
 }
 
-fn flood_fill<T, F>(start: T, succ: F) -> HashSet<T>
-where
-    T: Eq + Hash + Copy,
-    F: Fn(&T) -> Vec<T>,
-{
-    let mut stack = vec![start];
-    let mut seen = HashSet::from([start]);
-
-    while let Some(elem) = stack.pop() {
-        let neighbours = succ(&elem);
-        for n in neighbours {
-            if seen.insert(n) {
-                stack.push(n);
-            }
-        }
-    }
-
-    seen
-}
+/// For each tile, a bitmask of the directions beams were travelling
+/// when they entered it. Zero means the tile is not energised.
+fn beam_map(input: &Input, p: Position, d: Direction) -> Grid<u8> {
+    let seen = follow_beams(input, p, d);
 
-fn parse_input(s: &str) -> Input {
-    let lines = s.lines().map(|l| l.to_string()).collect::<Vec<_>>();
-    Input {
-        g: Grid::from_strings(&lines),
+    let mut beams = Grid::new(input.g.width, input.g.height(), 0u8);
+    for b in seen {
+        *beams.get_pos_mut(&b.p) |= b.d.bit();
     }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Grid<T> {
-    width: usize,
-    vec: Vec<T>,
-}
-
-impl Grid<char> {
-    fn from_strings(lines: &[String]) -> Self {
-        if lines.len() == 0 {
-            return Grid {
-                width: 0,
-                vec: vec![],
-            };
+    beams
+}
+
+/// The contraption with energised tiles drawn in bold yellow.
+fn render_ansi(g: &Grid<char>, beams: &Grid<u8>) -> String {
+    let mut out = String::new();
+    for y in 0..g.height() {
+        for x in 0..g.width {
+            let c = *g.get(x, y);
+            if *beams.get(x, y) != 0 {
+                out.push_str(&format!("\x1b[1;33m{}\x1b[0m", c));
+            } else {
+                out.push(c);
+            }
         }
-
-        let width = lines[0].len();
-        let vec = lines.iter().flat_map(|l| l.chars()).collect();
-        Grid { width, vec }
-    }
-}
-
-impl<T> Grid<T> {
-    fn height(&self) -> usize {
-        self.vec.len() / self.width
+        out.push('\n');
     }
+    out
+}
+
+/// The contraption with beams drawn over empty tiles,
+/// as an arrow if one beam passes through or a count if several do.
+fn render_dirs(g: &Grid<char>, beams: &Grid<u8>) -> String {
+    let mut out = String::new();
+    for y in 0..g.height() {
+        for x in 0..g.width {
+            let c = *g.get(x, y);
+            let mask = *beams.get(x, y);
+            if c != '.' || mask == 0 {
+                out.push(c);
+                continue;
+            }
 
-    fn to_vec_index(&self, x: usize, y: usize) -> Option<usize> {
-        if x < self.width && y < self.height() {
-            Some((y * self.width) + x)
-        } else {
-            None
+            let dirs = DIRECTIONS
+                .iter()
+                .filter(|d| mask & d.bit() != 0)
+                .collect::<Vec<_>>();
+            if dirs.len() == 1 {
+                out.push(dirs[0].arrow());
+            } else {
+                out.push(char::from_digit(dirs.len() as u32, 10).unwrap());
+            }
         }
+        out.push('\n');
     }
-
-    fn signed_to_vec_index(&self, x: i64, y: i64) -> Option<usize> {
-        let x2 = usize::try_from(x).ok()?;
-        let y2 = usize::try_from(y).ok()?;
-        self.to_vec_index(x2, y2)
-    }
-
-    fn pos_to_vec_index(&self, pos: &Position) -> Option<usize> {
-        self.signed_to_vec_index(pos.x, pos.y)
-    }
-
-    fn get(&self, x: usize, y: usize) -> &T {
-        &self.vec[self.to_vec_index(x, y).unwrap()]
-    }
-
-    fn get_pos(&self, pos: &Position) -> &T {
-        self.get(pos.x.try_into().unwrap(), pos.y.try_into().unwrap())
-    }
-
-    fn try_get_pos(&self, pos: &Position) -> Option<&T> {
-        self.pos_to_vec_index(pos).map(|i| &self.vec[i])
-    }
-}
-
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
-struct Position {
-    x: i64,
-    y: i64,
+    out
 }
 
-fn pos(x: i64, y: i64) -> Position {
-    Position { x, y }
-}
-
-impl Position {
-    fn move_in_direction(&self, direction: Direction) -> Position {
-        match direction {
-            Direction::Up => Position {
-                x: self.x,
-                y: self.y - 1,
-            },
-            Direction::Right => Position {
-                x: self.x + 1,
-                y: self.y,
-            },
-            Direction::Down => Position {
-                x: self.x,
-                y: self.y + 1,
-            },
-            Direction::Left => Position {
-                x: self.x - 1,
-                y: self.y,
-            },
-        }
+/// A greyscale PPM image with one pixel per tile,
+/// brighter the more beam directions crossed the tile.
+fn render_ppm(beams: &Grid<u8>) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", beams.width, beams.height()).into_bytes();
+    for mask in &beams.vec {
+        let level = (mask.count_ones() * 255 / 4) as u8;
+        out.extend([level, level, level]);
     }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
-    Up,
-    Right,
-    Down,
-    Left,
+    out
 }
 
 #[cfg(test)]
@@ -227,7 +135,7 @@ mod tests {
     }
 
     #[test]
-    fn test_succ() {
+    fn test_render_dirs() {
         let input_str = "\
 .|...\\....
 |.-.\\.....
@@ -241,14 +149,63 @@ mod tests {
 ..//.|....
 ";
         let input = parse_input(&input_str);
-        assert_eq!(
-            succ(&input.g, pos(5, 0), Direction::Right),
-            vec![(pos(5, 1), Direction::Down)]
-        );
+        let beams = beam_map(&input, pos(0, 0), Direction::Right);
 
         assert_eq!(
-            succ(&input.g, pos(4, 1), Direction::Down),
-            vec![(pos(5, 1), Direction::Right)]
+            render_dirs(&input.g, &beams),
+            "\
+→|←←←\\....
+|↓-.\\↑....
+.↓...|-→→→
+.↓...↓↑.|.
+.↓...↓↑...
+.↓...↓↑..\\
+.↓../2\\\\..
+←-→-/↓↓|..
+.|←←←2-|.\\
+.↓//.|.↓..
+"
         );
     }
+
+    #[test]
+    fn test_render_ppm() {
+        let input_str = "\
+.|...\\....
+|.-.\\.....
+.....|-...
+........|.
+..........
+.........\\
+..../.\\\\..
+.-.-/..|..
+.|....-|.\\
+..//.|....
+";
+        let input = parse_input(&input_str);
+        let beams = beam_map(&input, pos(0, 0), Direction::Right);
+        let ppm = render_ppm(&beams);
+
+        let header = b"P6\n10 10\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+
+        let pixel = |x: usize, y: usize| {
+            let i = header.len() + (y * 10 + x) * 3;
+            &ppm[i..i + 3]
+        };
+        assert_eq!(pixel(0, 0), [63, 63, 63]);
+        assert_eq!(pixel(5, 6), [127, 127, 127]);
+        assert_eq!(pixel(9, 0), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_variant_elements() {
+        let input_str = "\
+.a..#
+..>..
+a.+..
+";
+        let input = parse_input(&input_str);
+        assert_eq!(solve(&input), 8);
+    }
 }
@@ -1,7 +1,10 @@
 use std::{
-    collections::HashSet,
+    env,
     fs::{self},
-    hash::Hash,
+};
+
+use aoc23::day16::{
+    beam, parse_input, pos, succ, Beam, Direction, Grid, Input, Position, BEAMS_PER_TILE,
 };
 
 fn main() {
@@ -10,207 +13,277 @@ fn main() {
 
     let answer = solve(&input);
     println!("{}", answer);
-}
-
-#[derive(Debug)]
-struct Input {
-    g: Grid<char>,
-}
-
-fn reflect(d: Direction, val: char) -> Direction {
-    match (d, val) {
-        (Direction::Right, '/') => Direction::Up,
-        (Direction::Left, '/') => Direction::Down,
-        (Direction::Up, '/') => Direction::Right,
-        (Direction::Down, '/') => Direction::Left,
-
-        (Direction::Right, '\\') => Direction::Down,
-        (Direction::Left, '\\') => Direction::Up,
-        (Direction::Up, '\\') => Direction::Left,
-        (Direction::Down, '\\') => Direction::Right,
-        _ => panic!("unhandled"),
-    }
-}
-
-fn succ_dirs(val: &char, d: Direction) -> Vec<Direction> {
-    match (val, d) {
-        ('.', _) => vec![d],
-        ('/', _) | ('\\', _) => vec![reflect(d, *val)],
-        ('-', d) if d == Direction::Left || d == Direction::Right => vec![d],
-        ('|', d) if d == Direction::Up || d == Direction::Down => vec![d],
-        ('-', _) => vec![Direction::Left, Direction::Right],
-        ('|', _) => vec![Direction::Up, Direction::Down],
-        _ => panic!("unhandled"),
-    }
-}
-
-fn succ(g: &Grid<char>, p: Position, d: Direction) -> Vec<(Position, Direction)> {
-    let val = g.get_pos(&p);
-    let next_dirs = succ_dirs(val, d);
-
-    next_dirs
-        .iter()
-        .filter_map(|next_d| {
-            let new_p = p.move_in_direction(*next_d);
-            g.try_get_pos(&new_p).is_some().then_some((new_p, *next_d))
-        })
-        .collect()
-}
 
-fn count_energized(g: &Grid<char>, p: Position, d: Direction) -> usize {
-    let seen = flood_fill((p, d), |(p, d)| succ(g, *p, *d));
-    let energized = seen.iter().map(|x| x.0).collect::<HashSet<_>>();
-
-    energized.len()
-}
-
-fn flood_fill<T, F>(start: T, succ: F) -> HashSet<T>
-where
-    T: Eq + Hash + Copy,
-    F: Fn(&T) -> Vec<T>,
-{
-    let mut stack = vec![start];
-    let mut seen = HashSet::from([start]);
-
-    while let Some(elem) = stack.pop() {
-        let neighbours = succ(&elem);
-        for n in neighbours {
-            if seen.insert(n) {
-                stack.push(n);
-            }
+    // --table prints the energy for every entry point
+    if env::args().nth(1).as_deref() == Some("--table") {
+        for ((p, d), energy) in entry_energy_table(&input) {
+            println!("{},{} {:?}: {}", p.x, p.y, d, energy);
         }
     }
-
-    seen
 }
 
-fn solve(input: &Input) -> i64 {
-    (0..input.g.width)
+fn entry_points(g: &Grid<char>) -> Vec<(Position, Direction)> {
+    (0..g.width)
         .flat_map(|c| {
             vec![
                 (pos(c as i64, 0), Direction::Down),
-                (pos(c as i64, (input.g.height() as i64) - 1), Direction::Up),
+                (pos(c as i64, (g.height() as i64) - 1), Direction::Up),
             ]
         })
-        .chain((0..input.g.height()).flat_map(|r| {
+        .chain((0..g.height()).flat_map(|r| {
             vec![
                 (pos(0, r as i64), Direction::Right),
-                (pos((input.g.width as i64) - 1, r as i64), Direction::Left),
+                (pos((g.width as i64) - 1, r as i64), Direction::Left),
             ]
         }))
-        .map(|p| count_energized(&input.g, p.0, p.1))
+        .collect()
+}
+
+fn solve(input: &Input) -> i64 {
+    entry_energy_table(input)
+        .into_iter()
+        .map(|(_, energy)| energy)
         .max()
         .unwrap() as i64
 }
 
-fn parse_input(s: &str) -> Input {
-    let lines = s.lines().map(|l| l.to_string()).collect::<Vec<_>>();
-    Input {
-        g: Grid::from_strings(&lines),
-    }
+/// Energised tile count for every edge entry point.
+fn entry_energy_table(input: &Input) -> Vec<((Position, Direction), usize)> {
+    let graph = CondensedGraph::new(input);
+    entry_points(&input.g)
+        .into_iter()
+        .map(|(p, d)| {
+            (
+                (p, d),
+                graph.energized_from(beam(p, d).id(&input.g)).count(),
+            )
+        })
+        .collect()
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Grid<T> {
-    width: usize,
-    vec: Vec<T>,
+#[derive(Debug, Clone)]
+struct BitSet {
+    words: Vec<u64>,
 }
 
-impl Grid<char> {
-    fn from_strings(lines: &[String]) -> Self {
-        if lines.len() == 0 {
-            return Grid {
-                width: 0,
-                vec: vec![],
-            };
+impl BitSet {
+    fn new(size: usize) -> BitSet {
+        BitSet {
+            words: vec![0; size.div_ceil(64)],
         }
-
-        let width = lines[0].len();
-        let vec = lines.iter().flat_map(|l| l.chars()).collect();
-        Grid { width, vec }
     }
-}
 
-impl<T> Grid<T> {
-    fn height(&self) -> usize {
-        self.vec.len() / self.width
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
     }
 
-    fn to_vec_index(&self, x: usize, y: usize) -> Option<usize> {
-        if x < self.width && y < self.height() {
-            Some((y * self.width) + x)
-        } else {
-            None
+    fn union_with(&mut self, other: &BitSet) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a |= b;
         }
     }
 
-    fn signed_to_vec_index(&self, x: i64, y: i64) -> Option<usize> {
-        let x2 = usize::try_from(x).ok()?;
-        let y2 = usize::try_from(y).ok()?;
-        self.to_vec_index(x2, y2)
+    fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
     }
+}
 
-    fn pos_to_vec_index(&self, pos: &Position) -> Option<usize> {
-        self.signed_to_vec_index(pos.x, pos.y)
-    }
+// The beam graph over Beam states, collapsed into its
+// strongly connected components. Most components have a single successor,
+// so we only store the full energised set for "junction" components
+// that fan out (i.e. splitters) and walk the chains in between on demand.
+#[derive(Debug)]
+struct CondensedGraph {
+    tile_count: usize,
+    comp_of: Vec<usize>,
+    comp_tiles: Vec<Vec<usize>>,
+    comp_succs: Vec<Vec<usize>>,
+    junction_sets: Vec<Option<BitSet>>,
+}
+
+impl CondensedGraph {
+    fn new(input: &Input) -> CondensedGraph {
+        let g = &input.g;
+        let tile_count = g.vec.len();
+
+        let succs = (0..tile_count * BEAMS_PER_TILE)
+            .map(|id| {
+                succ(input, Beam::from_id(id, g))
+                    .iter()
+                    .map(|b| b.id(g))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Tarjan's algorithm gives us the components in reverse
+        // topological order, so everything a component can reach
+        // has already been processed by the time we get to it.
+        let comps = strongly_connected_components(&succs);
+
+        let mut comp_of = vec![0; succs.len()];
+        for (c, states) in comps.iter().enumerate() {
+            for s in states {
+                comp_of[*s] = c;
+            }
+        }
+
+        let comp_tiles = comps
+            .iter()
+            .map(|states| {
+                let mut tiles = states
+                    .iter()
+                    .map(|s| s / BEAMS_PER_TILE)
+                    .collect::<Vec<_>>();
+                tiles.sort();
+                tiles.dedup();
+                tiles
+            })
+            .collect::<Vec<_>>();
+
+        let comp_succs = comps
+            .iter()
+            .enumerate()
+            .map(|(c, states)| {
+                let mut next = states
+                    .iter()
+                    .flat_map(|s| succs[*s].iter().map(|n| comp_of[*n]))
+                    .filter(|n| *n != c)
+                    .collect::<Vec<_>>();
+                next.sort();
+                next.dedup();
+                next
+            })
+            .collect::<Vec<_>>();
+
+        let mut graph = CondensedGraph {
+            tile_count,
+            comp_of,
+            comp_tiles,
+            comp_succs,
+            junction_sets: vec![None; comps.len()],
+        };
+
+        for c in 0..comps.len() {
+            if graph.comp_succs[c].len() > 1 {
+                let mut set = BitSet::new(tile_count);
+                for t in &graph.comp_tiles[c] {
+                    set.insert(*t);
+                }
+                for n in &graph.comp_succs[c] {
+                    graph.walk_chain(*n, &mut set);
+                }
+                graph.junction_sets[c] = Some(set);
+            }
+        }
 
-    fn get(&self, x: usize, y: usize) -> &T {
-        &self.vec[self.to_vec_index(x, y).unwrap()]
+        graph
     }
 
-    fn get_pos(&self, pos: &Position) -> &T {
-        self.get(pos.x.try_into().unwrap(), pos.y.try_into().unwrap())
+    // Adds everything reachable from component c to the set,
+    // following single-successor components until we hit
+    // a junction or the beam leaves the grid.
+    fn walk_chain(&self, mut c: usize, set: &mut BitSet) {
+        loop {
+            if let Some(junction_set) = &self.junction_sets[c] {
+                set.union_with(junction_set);
+                return;
+            }
+
+            for t in &self.comp_tiles[c] {
+                set.insert(*t);
+            }
+
+            match self.comp_succs[c][..] {
+                [] => return,
+                [next] => c = next,
+                _ => panic!("junction {} has not been processed", c),
+            }
+        }
     }
 
-    fn try_get_pos(&self, pos: &Position) -> Option<&T> {
-        self.pos_to_vec_index(pos).map(|i| &self.vec[i])
+    fn energized_from(&self, state: usize) -> BitSet {
+        let mut set = BitSet::new(self.tile_count);
+        self.walk_chain(self.comp_of[state], &mut set);
+        set
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
-struct Position {
-    x: i64,
-    y: i64,
-}
+fn strongly_connected_components(succs: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    // Iterative version of Tarjan's algorithm,
+    // since the chains are far too long to recurse over.
+    let unvisited = usize::MAX;
+    let mut index = vec![unvisited; succs.len()];
+    let mut lowlink = vec![0; succs.len()];
+    let mut on_stack = vec![false; succs.len()];
+    let mut stack = vec![];
+    let mut comps = vec![];
+    let mut next_index = 0;
+
+    for root in 0..succs.len() {
+        if index[root] != unvisited {
+            continue;
+        }
 
-fn pos(x: i64, y: i64) -> Position {
-    Position { x, y }
-}
+        index[root] = next_index;
+        lowlink[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        let mut call_stack = vec![(root, 0)];
+
+        while let Some((v, i)) = call_stack.last_mut() {
+            let v = *v;
+            if let Some(&w) = succs[v].get(*i) {
+                *i += 1;
+                if index[w] == unvisited {
+                    index[w] = next_index;
+                    lowlink[w] = next_index;
+                    next_index += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    call_stack.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[v] = lowlink[v].min(index[w]);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some((u, _)) = call_stack.last() {
+                lowlink[*u] = lowlink[*u].min(lowlink[v]);
+            }
 
-impl Position {
-    fn move_in_direction(&self, direction: Direction) -> Position {
-        match direction {
-            Direction::Up => Position {
-                x: self.x,
-                y: self.y - 1,
-            },
-            Direction::Right => Position {
-                x: self.x + 1,
-                y: self.y,
-            },
-            Direction::Down => Position {
-                x: self.x,
-                y: self.y + 1,
-            },
-            Direction::Left => Position {
-                x: self.x - 1,
-                y: self.y,
-            },
+            if lowlink[v] == index[v] {
+                let mut comp = vec![];
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    comp.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                comps.push(comp);
+            }
         }
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
-    Up,
-    Right,
-    Down,
-    Left,
+    comps
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc23::day16::count_energized;
+
+    // Runs a separate flood fill for every entry point.
+    fn solve_brute_force(input: &Input) -> i64 {
+        entry_points(&input.g)
+            .into_iter()
+            .map(|p| count_energized(input, p.0, p.1))
+            .max()
+            .unwrap() as i64
+    }
 
     #[test]
     fn test_solve1() {
@@ -233,7 +306,7 @@ mod tests {
     }
 
     #[test]
-    fn test_succ() {
+    fn test_entry_energy_table() {
         let input_str = "\
 .|...\\....
 |.-.\\.....
@@ -247,14 +320,26 @@ mod tests {
 ..//.|....
 ";
         let input = parse_input(&input_str);
-        assert_eq!(
-            succ(&input.g, pos(5, 0), Direction::Right),
-            vec![(pos(5, 1), Direction::Down)]
-        );
-
-        assert_eq!(
-            succ(&input.g, pos(4, 1), Direction::Down),
-            vec![(pos(5, 1), Direction::Right)]
-        );
+        let table = entry_energy_table(&input);
+
+        assert_eq!(table.len(), 40);
+        for ((p, d), energy) in table {
+            assert_eq!(energy, count_energized(&input, p, d));
+        }
+        assert_eq!(solve(&input), solve_brute_force(&input));
+    }
+
+    #[test]
+    fn test_entry_energy_table_variant_elements() {
+        let input_str = "\
+.a..#.
+..>.+b
+a.+..\\
+.b./<-
+";
+        let input = parse_input(&input_str);
+        for ((p, d), energy) in entry_energy_table(&input) {
+            assert_eq!(energy, count_energized(&input, p, d));
+        }
     }
 }
@@ -18,6 +18,7 @@ struct Input {
     g: Grid<i64>,
 }
 
+#[cfg(test)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct State {
     p: Position,
@@ -25,11 +26,10 @@ struct State {
     moves_in_dir_taken: i64,
 }
 
-// This new successor function makes the search slow.
-// It could be optimized by skipping over the intermediate states
-// when turning (must travel at least 4 in a straight line afterwards),
-// but compiling in release mode made the program run fast enough
-// so I didn't bother.
+// This new successor function makes the search slow
+// because it steps through the forced straight moves one tile at a time.
+// See succ_jump for the version that skips the intermediate states.
+#[cfg(test)]
 fn succ(g: &Grid<i64>, s: &State) -> Vec<(State, i64)> {
     let succs = DIRECTIONS
         .iter()
@@ -76,8 +76,139 @@ fn succ(g: &Grid<i64>, s: &State) -> Vec<(State, i64)> {
     succs
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    fn other(&self) -> Axis {
+        match self {
+            Axis::Horizontal => Axis::Vertical,
+            Axis::Vertical => Axis::Horizontal,
+        }
+    }
+
+    fn directions(&self) -> [Direction; 2] {
+        match self {
+            Axis::Horizontal => [Direction::Left, Direction::Right],
+            Axis::Vertical => [Direction::Up, Direction::Down],
+        }
+    }
+}
+
+// Since every straight run is followed by a turn,
+// we only need to know which axis we arrived on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct JumpState {
+    p: Position,
+    axis: Axis,
+}
+
+const MIN_STRAIGHT: i64 = 4;
+const MAX_STRAIGHT: i64 = 10;
+
+// Running totals of heat loss along each row and column,
+// so that the cost of a straight run is a single subtraction.
+// row_sums[x, y] is the sum of g[0..x, y],
+// col_sums[x, y] is the sum of g[x, 0..y].
+#[derive(Debug)]
+struct PrefixSums {
+    row_sums: Grid<i64>,
+    col_sums: Grid<i64>,
+}
+
+impl PrefixSums {
+    fn new(g: &Grid<i64>) -> PrefixSums {
+        let width = g.width;
+        let height = g.height();
+
+        let mut row_sums = Grid {
+            width: width + 1,
+            vec: vec![0; (width + 1) * height],
+        };
+        let mut col_sums = Grid {
+            width,
+            vec: vec![0; width * (height + 1)],
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let cost = *g.get(x, y);
+                *row_sums.get_mut(x + 1, y) = *row_sums.get(x, y) + cost;
+                *col_sums.get_mut(x, y + 1) = *col_sums.get(x, y) + cost;
+            }
+        }
+
+        PrefixSums { row_sums, col_sums }
+    }
+
+    // Heat lost moving from p to dest in a straight line,
+    // counting dest but not p.
+    fn run_cost(&self, p: &Position, dest: &Position) -> i64 {
+        if p.y == dest.y {
+            let y = p.y as usize;
+            let (lo, hi) = if dest.x > p.x {
+                (p.x + 1, dest.x + 1)
+            } else {
+                (dest.x, p.x)
+            };
+            self.row_sums.get(hi as usize, y) - self.row_sums.get(lo as usize, y)
+        } else {
+            let x = p.x as usize;
+            let (lo, hi) = if dest.y > p.y {
+                (p.y + 1, dest.y + 1)
+            } else {
+                (dest.y, p.y)
+            };
+            self.col_sums.get(x, hi as usize) - self.col_sums.get(x, lo as usize)
+        }
+    }
+}
+
+// Turns onto the other axis and then travels between
+// MIN_STRAIGHT and MAX_STRAIGHT tiles in one move.
+fn succ_jump(g: &Grid<i64>, sums: &PrefixSums, s: &JumpState) -> Vec<(JumpState, i64)> {
+    let axis = s.axis.other();
+    axis.directions()
+        .iter()
+        .flat_map(|d| {
+            (MIN_STRAIGHT..=MAX_STRAIGHT).filter_map(move |k| {
+                let dest = s.p.move_distance(*d, k);
+                g.try_get_pos(&dest)?;
+                Some((JumpState { p: dest, axis }, sums.run_cost(&s.p, &dest)))
+            })
+        })
+        .collect()
+}
+
 fn solve(input: &Input) -> i64 {
     let goal = pos((input.g.width - 1) as i64, (input.g.height() - 1) as i64);
+    let sums = PrefixSums::new(&input.g);
+
+    dijkstra_search(
+        &[
+            JumpState {
+                p: pos(0, 0),
+                axis: Axis::Horizontal,
+            },
+            JumpState {
+                p: pos(0, 0),
+                axis: Axis::Vertical,
+            },
+        ],
+        |s| succ_jump(&input.g, &sums, s),
+        |s| s.p == goal,
+    )
+    .unwrap()
+}
+
+// The original one-tile-at-a-time search,
+// kept around to check succ_jump against.
+#[cfg(test)]
+fn solve_stepwise(input: &Input) -> i64 {
+    let goal = pos((input.g.width - 1) as i64, (input.g.height() - 1) as i64);
 
     dijkstra_search(
         &vec![
@@ -178,6 +309,7 @@ fn pos(x: i64, y: i64) -> Position {
 }
 
 impl Position {
+    #[cfg(test)]
     fn move_in_direction(&self, direction: Direction) -> Position {
         match direction {
             Direction::Up => Position {
@@ -198,6 +330,15 @@ impl Position {
             },
         }
     }
+
+    fn move_distance(&self, direction: Direction, distance: i64) -> Position {
+        match direction {
+            Direction::Up => pos(self.x, self.y - distance),
+            Direction::Right => pos(self.x + distance, self.y),
+            Direction::Down => pos(self.x, self.y + distance),
+            Direction::Left => pos(self.x - distance, self.y),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -209,6 +350,7 @@ enum Direction {
 }
 
 impl Direction {
+    #[cfg(test)]
     fn reverse(&self) -> Direction {
         match self {
             Direction::Up => Direction::Down,
@@ -219,6 +361,7 @@ impl Direction {
     }
 }
 
+#[cfg(test)]
 const DIRECTIONS: [Direction; 4] = [
     Direction::Up,
     Direction::Right,
@@ -272,6 +415,16 @@ impl<T> Grid<T> {
     fn try_get_pos(&self, pos: &Position) -> Option<&T> {
         self.pos_to_vec_index(pos).map(|i| &self.vec[i])
     }
+
+    fn get(&self, x: usize, y: usize) -> &T {
+        let i = self.to_vec_index(x, y).unwrap();
+        &self.vec[i]
+    }
+
+    fn get_mut(&mut self, x: usize, y: usize) -> &mut T {
+        let i = self.to_vec_index(x, y).unwrap();
+        &mut self.vec[i]
+    }
 }
 
 #[cfg(test)]
@@ -315,4 +468,50 @@ mod tests {
 
         assert_eq!(answer, 71);
     }
+
+    #[test]
+    fn test_solve_matches_stepwise() {
+        let inputs = [
+            "\
+2413432311323
+3215453535623
+3255245654254
+3446585845452
+4546657867536
+1438598798454
+4457876987766
+3637877979653
+4654967986887
+4564679986453
+1224686865563
+2546548887735
+4322674655533
+",
+            "\
+111111111111
+999999999991
+999999999991
+999999999991
+999999999991
+",
+            "\
+975653952147759
+673913439946922
+572983796176161
+861742944961525
+499935566362561
+161858145528193
+787345324195661
+372557829294495
+351878497982844
+864619716394663
+457682929149388
+",
+        ];
+
+        for input_str in inputs {
+            let input = parse_input(input_str);
+            assert_eq!(solve(&input), solve_stepwise(&input));
+        }
+    }
 }
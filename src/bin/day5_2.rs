@@ -1,4 +1,4 @@
-use std::{fs::File, io::BufRead, io::BufReader};
+use std::{env, fmt, fs::File, io::BufRead, io::BufReader, process};
 
 fn main() {
     let file = File::open("data/day5/input").unwrap();
@@ -6,15 +6,31 @@ fn main() {
     let lines = reader.lines().map(|l| l.unwrap()).collect::<Vec<_>>();
 
     let seeds = parse_seeds(&lines[0]);
+    let maps = parse_maps(&lines[2..]);
+    let seed_to_location = compose_all(&maps);
 
-    let maps = lines[2..]
-        .split(|l| l == "")
-        .map(|c| parse_chunk(c))
-        .collect::<Vec<_>>();
+    // --table prints the composed seed to location map,
+    // --seed N prints where seed N ends up
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(|a| a.as_str()) {
+        Some("--table") => {
+            print!("{}", seed_to_location);
+            return;
+        }
+        Some("--seed") => {
+            let Some(seed) = args.get(1).and_then(|a| a.parse::<i64>().ok()) else {
+                eprintln!("usage: day5_2 --seed <number>");
+                process::exit(1);
+            };
+            println!("{}", seed_to_location.apply(seed));
+            return;
+        }
+        _ => (),
+    }
 
     let answer = seeds
         .iter()
-        .flat_map(|s| seed_range_to_location_range(s, &maps))
+        .flat_map(|s| seed_to_location.apply_range(s))
         .map(|i| i.first)
         .min()
         .unwrap();
@@ -56,6 +72,132 @@ impl Interval {
     }
 }
 
+/// Every value in src moves by offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Piece {
+    src: Interval,
+    offset: i64,
+}
+
+/// A map over every i64, as pieces sorted by src that don't overlap
+/// and leave no gaps. Values no lookup covers get a piece with offset 0,
+/// and neighbouring pieces never share an offset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PiecewiseMap {
+    pieces: Vec<Piece>,
+}
+
+impl PiecewiseMap {
+    fn identity() -> PiecewiseMap {
+        PiecewiseMap {
+            pieces: vec![Piece {
+                src: Interval::new(i64::MIN, i64::MAX),
+                offset: 0,
+            }],
+        }
+    }
+
+    /// One stage of the almanac. Where lookups overlap the first one wins,
+    /// same as in translate.
+    fn from_lookups(table: &[Lookup]) -> PiecewiseMap {
+        let mut pieces = vec![];
+        let mut remaining = vec![Interval::new(i64::MIN, i64::MAX)];
+        for lookup in table {
+            let mut new_remaining = vec![];
+            for interval in remaining {
+                let (a, b, c) = slice_interval(&interval, &lookup.src);
+                if let Some(b) = b {
+                    pieces.push(Piece {
+                        src: b,
+                        offset: lookup.dst_start - lookup.src.first,
+                    });
+                }
+                new_remaining.extend(a);
+                new_remaining.extend(c);
+            }
+            remaining = new_remaining;
+        }
+        pieces.extend(remaining.into_iter().map(|src| Piece { src, offset: 0 }));
+
+        PiecewiseMap::normalise(pieces)
+    }
+
+    /// Sorts the pieces and merges neighbours that move by the same amount.
+    fn normalise(mut pieces: Vec<Piece>) -> PiecewiseMap {
+        pieces.sort_by_key(|p| p.src.first);
+
+        let mut out: Vec<Piece> = vec![];
+        for p in pieces {
+            match out.last_mut() {
+                Some(last) if last.offset == p.offset && last.src.last + 1 == p.src.first => {
+                    last.src.last = p.src.last;
+                }
+                _ => out.push(p),
+            }
+        }
+        PiecewiseMap { pieces: out }
+    }
+
+    /// The pieces that overlap the interval, in order.
+    fn pieces_overlapping(&self, i: Interval) -> impl Iterator<Item = &Piece> {
+        let start = self.pieces.partition_point(|p| p.src.last < i.first);
+        self.pieces[start..]
+            .iter()
+            .take_while(move |p| p.src.first <= i.last)
+    }
+
+    /// This map followed by other.
+    fn then(&self, other: &PiecewiseMap) -> PiecewiseMap {
+        let pieces = self
+            .pieces
+            .iter()
+            .flat_map(|p| {
+                let image = p.src.offset(p.offset);
+                other
+                    .pieces_overlapping(image)
+                    .map(|q| {
+                        let overlap = slice_interval(&image, &q.src).1.unwrap();
+                        Piece {
+                            src: overlap.offset(-p.offset),
+                            offset: p.offset + q.offset,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        PiecewiseMap::normalise(pieces)
+    }
+
+    fn apply(&self, val: i64) -> i64 {
+        let i = Interval::new(val, val);
+        val + self.pieces_overlapping(i).next().unwrap().offset
+    }
+
+    /// Where a whole range ends up, as one interval per piece it crosses.
+    fn apply_range(&self, i: &Interval) -> Vec<Interval> {
+        self.pieces_overlapping(*i)
+            .map(|p| slice_interval(i, &p.src).1.unwrap().offset(p.offset))
+            .collect()
+    }
+}
+
+impl fmt::Display for PiecewiseMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for p in &self.pieces {
+            writeln!(f, "{}..={} {:+}", p.src.first, p.src.last, p.offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// All the stages squashed into a single seed to location map.
+fn compose_all(maps: &[Vec<Lookup>]) -> PiecewiseMap {
+    maps.iter().fold(PiecewiseMap::identity(), |acc, m| {
+        acc.then(&PiecewiseMap::from_lookups(m))
+    })
+}
+
 fn slice_interval(
     a: &Interval,
     b: &Interval,
@@ -90,6 +232,7 @@ fn slice_interval(
     }
 }
 
+#[cfg(test)]
 fn translate_single(
     i: &Interval,
     table: &Lookup,
@@ -105,6 +248,7 @@ fn translate_single(
     (a, b, c)
 }
 
+#[cfg(test)]
 fn translate(num: &Vec<Interval>, table: &Vec<Lookup>) -> Vec<Interval> {
     let mut v = Vec::new();
 
@@ -134,6 +278,7 @@ fn translate(num: &Vec<Interval>, table: &Vec<Lookup>) -> Vec<Interval> {
     v
 }
 
+#[cfg(test)]
 fn seed_range_to_location_range(s: &Interval, maps: &Vec<Vec<Lookup>>) -> Vec<Interval> {
     maps.iter().fold(vec![*s], |s, m| translate(&s, m))
 }
@@ -151,6 +296,13 @@ fn parse_seeds(line: &str) -> Vec<Interval> {
         .collect()
 }
 
+fn parse_maps(lines: &[String]) -> Vec<Vec<Lookup>> {
+    lines
+        .split(|l| l == "")
+        .map(|c| parse_chunk(c))
+        .collect::<Vec<_>>()
+}
+
 fn parse_chunk(c: &[String]) -> Vec<Lookup> {
     let vals = &c[1..];
     vals.iter()
@@ -214,4 +366,123 @@ mod tests {
 
         assert_eq!(result, vec![Interval::new(81, 94)]);
     }
+
+    #[test]
+    fn test_from_lookups() {
+        let input_str = "\
+seed-to-soil map:
+50 98 2
+52 50 48
+";
+        let lines = input_str.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        let maps = parse_maps(&lines);
+        let map = PiecewiseMap::from_lookups(&maps[0]);
+
+        assert_eq!(
+            map.pieces,
+            vec![
+                Piece {
+                    src: Interval::new(i64::MIN, 49),
+                    offset: 0
+                },
+                Piece {
+                    src: Interval::new(50, 97),
+                    offset: 2
+                },
+                Piece {
+                    src: Interval::new(98, 99),
+                    offset: -48
+                },
+                Piece {
+                    src: Interval::new(100, i64::MAX),
+                    offset: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compose_all() {
+        let input_str = "\
+seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
+        let lines = input_str.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        let seeds = parse_seeds(&lines[0]);
+        let maps = parse_maps(&lines[2..]);
+        let composed = compose_all(&maps);
+
+        // sorted, touching end to end, and covering everything
+        assert_eq!(composed.pieces[0].src.first, i64::MIN);
+        assert_eq!(composed.pieces.last().unwrap().src.last, i64::MAX);
+        for w in composed.pieces.windows(2) {
+            assert_eq!(w[0].src.last + 1, w[1].src.first);
+            assert_ne!(w[0].offset, w[1].offset);
+        }
+
+        assert_eq!(composed.apply(79), 82);
+        assert_eq!(composed.apply(14), 43);
+        assert_eq!(composed.apply(55), 86);
+        assert_eq!(composed.apply(13), 35);
+
+        for s in &seeds {
+            let mut composed_ranges = composed.apply_range(s);
+            let mut stepwise = seed_range_to_location_range(s, &maps);
+            composed_ranges.sort_by_key(|i| i.first);
+            stepwise.sort_by_key(|i| i.first);
+            assert_eq!(
+                PiecewiseMap::normalise(
+                    composed_ranges
+                        .into_iter()
+                        .map(|src| Piece { src, offset: 0 })
+                        .collect()
+                ),
+                PiecewiseMap::normalise(
+                    stepwise
+                        .into_iter()
+                        .map(|src| Piece { src, offset: 0 })
+                        .collect()
+                )
+            );
+        }
+
+        let answer = seeds
+            .iter()
+            .flat_map(|s| composed.apply_range(s))
+            .map(|i| i.first)
+            .min()
+            .unwrap();
+        assert_eq!(answer, 46);
+    }
 }
@@ -1,7 +1,6 @@
-use std::{fs::File, io::BufRead, io::BufReader};
+use std::{fs::File, io::BufRead, io::BufReader, process};
 
-use lazy_static::lazy_static;
-use regex::Regex;
+use aoc23::day8::{parse_instructions, parse_node, Network};
 
 fn main() {
     let file = File::open("data/day8/input").unwrap();
@@ -12,73 +11,51 @@ fn main() {
     lines.next();
 
     let nodes = lines.map(|l| parse_node(&l)).collect::<Vec<_>>();
+    let network = match Network::new(instructions, &nodes) {
+        Ok(network) => network,
+        Err(diagnostics) => {
+            for d in diagnostics {
+                eprintln!("{:?}", d);
+            }
+            process::exit(1);
+        }
+    };
 
-    let result = follow_path(&instructions, &nodes);
-
-    println!("{}", result);
+    match follow_path(&network) {
+        Some(result) => println!("{}", result),
+        None => {
+            eprintln!("ZZZ can't be reached from AAA");
+            process::exit(1);
+        }
+    }
 }
 
-fn follow_path(instructions: &[Instruction], nodes: &[Node]) -> i64 {
+/// Steps from AAA to ZZZ, or None if the instructions never get there.
+fn follow_path(network: &Network) -> Option<u64> {
     let mut idx = 0;
 
-    let mut curr_node: &str = "AAA";
+    let mut curr_node = network.id("AAA")?;
+    let goal = network.id("ZZZ")?;
 
-    let mut count = 0;
-    while curr_node != "ZZZ" {
-        let node_info = nodes.iter().find(|n| n.name == curr_node).unwrap();
+    // After this many steps we must have been in some
+    // (node, instruction index) state twice, so we're going round in circles.
+    let limit = (network.len() * network.instructions.len()) as u64;
 
-        let i = instructions[idx];
-        let next = match i {
-            Instruction::Left => &node_info.left,
-            Instruction::Right => &node_info.right,
-        };
+    let mut count = 0;
+    while curr_node != goal {
+        if count >= limit {
+            return None;
+        }
 
-        curr_node = next;
+        curr_node = network.step(curr_node, idx);
 
         idx += 1;
-        if idx >= instructions.len() {
+        if idx >= network.instructions.len() {
             idx = 0;
         }
 
         count += 1;
     }
 
-    return count;
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Instruction {
-    Left,
-    Right,
-}
-
-lazy_static! {
-    static ref NODE_REGEX: Regex = Regex::new(r"^(\w+) = \((\w+), (\w+)\)").unwrap();
-}
-
-#[derive(Debug)]
-struct Node {
-    name: String,
-    left: String,
-    right: String,
-}
-
-fn parse_node(line: &str) -> Node {
-    let captures = NODE_REGEX.captures(line).unwrap();
-
-    Node {
-        name: captures[1].to_string(),
-        left: captures[2].to_string(),
-        right: captures[3].to_string(),
-    }
-}
-
-fn parse_instructions(line: &str) -> Vec<Instruction> {
-    line.chars()
-        .map(|c| match c {
-            'L' => Instruction::Left,
-            'R' => Instruction::Right,
-            _ => panic!("invalid instruction"),
-        })
-        .collect()
+    Some(count)
 }
@@ -1,39 +1,90 @@
-use std::{fs::File, io::BufRead, io::BufReader, iter::successors};
+use std::{env, fs::File, io::BufRead, io::BufReader, process};
+
+use aoc23::day9::{classify, extrapolate, parse_line, sum_results, Classification};
 
 fn main() {
     let file = File::open("data/day9/input").unwrap();
     let reader = BufReader::new(file);
-    let lines = reader.lines().map(|l| l.unwrap());
-
-    let result = lines
-        .map(|line| {
-            line.split_ascii_whitespace()
-                .map(|x| x.parse::<i64>().unwrap())
-                .collect::<Vec<_>>()
-        })
-        .map(|t| find_next_val(&t))
-        .sum::<i64>();
+    let lines = reader
+        .lines()
+        .map(|l| parse_line(&l.unwrap()))
+        .collect::<Vec<_>>();
 
-    println!("{}", result);
-}
+    // day9_2 [--at N] [--describe]
+    // N is the index to extrapolate to, the first value being index 0.
+    // It defaults to the puzzle's -1.
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut at = -1;
+    let mut describe = false;
 
-fn gen_deltas(line: &[i64]) -> Vec<i64> {
-    line.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>()
-}
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--at" => {
+                let Some(n) = args.get(i + 1).and_then(|a| a.parse::<i128>().ok()) else {
+                    eprintln!("usage: day9_2 [--at N] [--describe]");
+                    process::exit(1);
+                };
+                at = n;
+                i += 1;
+            }
+            "--describe" => describe = true,
+            a => panic!("unknown option: {}", a),
+        }
+        i += 1;
+    }
 
-fn gen_all_deltas(line: &[i64]) -> Vec<Vec<i64>> {
-    successors(Some(Vec::from(line)), |prev| {
-        if prev.iter().copied().all(|x| x == 0) {
-            None
-        } else {
-            Some(gen_deltas(prev))
+    if describe {
+        for (i, values) in lines.iter().enumerate() {
+            match values
+                .as_ref()
+                .map_err(|e| e.clone())
+                .and_then(|v| classify(v))
+            {
+                Ok(Classification::Polynomial(p)) => {
+                    let coefficients = p
+                        .coefficients()
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>();
+                    let value = p.eval(at).map_or("too big".to_string(), |v| v.to_string());
+                    println!(
+                        "{}: degree {}, coefficients [{}], value at {} is {}",
+                        i + 1,
+                        p.degree(),
+                        coefficients.join(", "),
+                        at,
+                        value
+                    );
+                }
+                Ok(Classification::Geometric(ratio)) => {
+                    println!("{}: geometric, ratio {}", i + 1, ratio);
+                }
+                Ok(Classification::Neither { degree, residual }) => {
+                    println!(
+                        "{}: neither, best fit degree {} is off by {}",
+                        i + 1,
+                        degree,
+                        residual
+                    );
+                }
+                Err(e) => println!("{}: {:?}", i + 1, e),
+            }
         }
-    })
-    .collect::<Vec<_>>()
-}
+        return;
+    }
 
-fn find_next_val(line: &[i64]) -> i64 {
-    gen_all_deltas(line)
-        .iter()
-        .rfold(0, |acc, x| x.first().unwrap() - acc)
+    let results = lines
+        .into_iter()
+        .map(|values| values.and_then(|v| extrapolate(&v, at)));
+
+    match sum_results(results) {
+        Ok(result) => println!("{}", result),
+        Err(errors) => {
+            for (line, e) in errors {
+                eprintln!("line {}: {:?}", line, e);
+            }
+            process::exit(1);
+        }
+    }
 }
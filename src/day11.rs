@@ -0,0 +1,273 @@
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Position {
+    pub x: i128,
+    pub y: i128,
+}
+
+impl Position {
+    pub fn manhattan_distance(&self, other: &Position) -> u128 {
+        let delta_x = other.x.abs_diff(self.x);
+        let delta_y = other.y.abs_diff(self.y);
+        delta_x + delta_y
+    }
+}
+
+#[derive(Debug)]
+pub struct GalaxyMap {
+    /// Galaxies in reading order, so galaxy n (counting from 1)
+    /// is galaxies[n - 1].
+    pub galaxies: Vec<Position>,
+    empty_rows: Vec<i128>,
+    empty_cols: Vec<i128>,
+}
+
+impl GalaxyMap {
+    pub fn parse(s: &str) -> GalaxyMap {
+        let lines = s.lines().collect::<Vec<_>>();
+        let width = lines.first().map(|l| l.len()).unwrap_or(0);
+
+        let galaxies = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(y, l)| {
+                l.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| Position {
+                        x: x as i128,
+                        y: y as i128,
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let empty_rows = (0..lines.len() as i128)
+            .filter(|row| galaxies.iter().all(|g| g.y != *row))
+            .collect();
+        let empty_cols = (0..width as i128)
+            .filter(|col| galaxies.iter().all(|g| g.x != *col))
+            .collect();
+
+        GalaxyMap {
+            galaxies,
+            empty_rows,
+            empty_cols,
+        }
+    }
+
+    /// Galaxy positions after every empty row is replaced by row_factor rows
+    /// and every empty column by col_factor columns.
+    /// The coordinates are i128 so that any u64 factor fits.
+    pub fn expand(&self, row_factor: u64, col_factor: u64) -> Vec<Position> {
+        self.galaxies
+            .iter()
+            .map(|p| {
+                let offset_x = self.empty_cols.iter().filter(|c| **c < p.x).count() as i128;
+                let offset_y = self.empty_rows.iter().filter(|r| **r < p.y).count() as i128;
+                Position {
+                    x: p.x + (offset_x * (col_factor as i128 - 1)),
+                    y: p.y + (offset_y * (row_factor as i128 - 1)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sum of the distances between every pair of galaxies.
+///
+/// Manhattan distance splits into separate x and y parts,
+/// and once the coordinates on an axis are sorted,
+/// each one is that far from everything before it,
+/// so we can add them all up with a running total.
+pub fn sum_of_distances(galaxies: &[Position]) -> u128 {
+    let axis_sum = |mut coords: Vec<i128>| {
+        coords.sort();
+        let mut total = 0;
+        let mut prefix = 0;
+        for (i, c) in coords.iter().enumerate() {
+            total += (c * i as i128) - prefix;
+            prefix += c;
+        }
+        total as u128
+    };
+
+    axis_sum(galaxies.iter().map(|g| g.x).collect())
+        + axis_sum(galaxies.iter().map(|g| g.y).collect())
+}
+
+/// Distance between galaxy a and galaxy b, numbered from 1,
+/// or None if there's no galaxy with one of those numbers.
+pub fn distance(galaxies: &[Position], a: usize, b: usize) -> Option<u128> {
+    let a = galaxies.get(a.checked_sub(1)?)?;
+    let b = galaxies.get(b.checked_sub(1)?)?;
+    Some(a.manhattan_distance(b))
+}
+
+/// For each galaxy, the number of its nearest other galaxy and the distance to it,
+/// or None if it's the only galaxy. This just checks every pair.
+pub fn nearest(galaxies: &[Position]) -> Vec<Option<(usize, u128)>> {
+    galaxies
+        .iter()
+        .enumerate()
+        .map(|(i, g)| {
+            galaxies
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(j, g2)| (j + 1, g.manhattan_distance(g2)))
+                .min_by_key(|(_, d)| *d)
+        })
+        .collect()
+}
+
+/// For each galaxy, the number of the farthest galaxy and the distance to it,
+/// or None if it's the only galaxy.
+///
+/// The farthest point in Manhattan distance is always one of the galaxies
+/// with the largest or smallest x + y or x - y, so we only need to check those four.
+pub fn farthest(galaxies: &[Position]) -> Vec<Option<(usize, u128)>> {
+    if galaxies.len() < 2 {
+        return vec![None; galaxies.len()];
+    }
+
+    let extremes = [|g: &Position| g.x + g.y, |g: &Position| g.x - g.y]
+        .iter()
+        .flat_map(|key| {
+            let by_key = |a: &(usize, &Position), b: &(usize, &Position)| key(a.1).cmp(&key(b.1));
+            [
+                galaxies.iter().enumerate().min_by(by_key).unwrap().0,
+                galaxies.iter().enumerate().max_by(by_key).unwrap().0,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    galaxies
+        .iter()
+        .map(|g| {
+            extremes
+                .iter()
+                .map(|j| (j + 1, g.manhattan_distance(&galaxies[*j])))
+                .max_by_key(|(_, d)| *d)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sum_of_distances_slow(galaxies: &[Position]) -> u128 {
+        galaxies
+            .iter()
+            .flat_map(|g| galaxies.iter().map(|g2| g2.manhattan_distance(g)))
+            .sum::<u128>()
+            / 2
+    }
+
+    #[test]
+    fn test_sum_of_distances() {
+        let input_str = "\
+...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....
+";
+        let map = GalaxyMap::parse(input_str);
+
+        assert_eq!(sum_of_distances(&map.expand(2, 2)), 374);
+        assert_eq!(sum_of_distances(&map.expand(10, 10)), 1030);
+        assert_eq!(sum_of_distances(&map.expand(100, 100)), 8410);
+
+        let galaxies = map.expand(3, 7);
+        assert_eq!(
+            sum_of_distances(&galaxies),
+            sum_of_distances_slow(&galaxies)
+        );
+
+        // each extra row or column of space adds 82 to the total
+        let galaxies = map.expand(100000000000000, 100000000000000);
+        assert_eq!(sum_of_distances(&galaxies), 292 + (82 * 99999999999999));
+        let galaxies = map.expand(u64::MAX, u64::MAX);
+        assert_eq!(
+            sum_of_distances(&galaxies),
+            292 + (82 * (u64::MAX as u128 - 1))
+        );
+        assert_eq!(
+            sum_of_distances(&galaxies),
+            sum_of_distances_slow(&galaxies)
+        );
+    }
+
+    #[test]
+    fn test_queries() {
+        let input_str = "\
+...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....
+";
+        let map = GalaxyMap::parse(input_str);
+        let galaxies = map.expand(2, 2);
+
+        assert_eq!(distance(&galaxies, 5, 9), Some(9));
+        assert_eq!(distance(&galaxies, 1, 7), Some(15));
+        assert_eq!(distance(&galaxies, 3, 6), Some(17));
+        assert_eq!(distance(&galaxies, 8, 9), Some(5));
+
+        let nearest = nearest(&galaxies);
+        let farthest = farthest(&galaxies);
+        for (i, g) in galaxies.iter().enumerate() {
+            let dists = galaxies
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, g2)| g.manhattan_distance(g2))
+                .collect::<Vec<_>>();
+            let (n, n_dist) = nearest[i].unwrap();
+            let (f, f_dist) = farthest[i].unwrap();
+            assert_eq!(n_dist, *dists.iter().min().unwrap());
+            assert_eq!(f_dist, *dists.iter().max().unwrap());
+            assert_eq!(distance(&galaxies, i + 1, n), Some(n_dist));
+            assert_eq!(distance(&galaxies, i + 1, f), Some(f_dist));
+        }
+    }
+
+    #[test]
+    fn test_queries_edge_cases() {
+        let input_str = "\
+...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....
+";
+        let galaxies = GalaxyMap::parse(input_str).expand(2, 2);
+        assert_eq!(distance(&galaxies, 0, 1), None);
+        assert_eq!(distance(&galaxies, 1, 10), None);
+        assert_eq!(distance(&galaxies, 9, 9), Some(0));
+
+        let galaxies = GalaxyMap::parse("..\n.#\n").expand(2, 2);
+        assert_eq!(nearest(&galaxies), vec![None]);
+        assert_eq!(farthest(&galaxies), vec![None]);
+
+        let galaxies = GalaxyMap::parse("..\n..\n").expand(2, 2);
+        assert_eq!(nearest(&galaxies), vec![]);
+        assert_eq!(farthest(&galaxies), vec![]);
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+#[derive(Debug)]
+pub struct Input {
+    pub g: Grid<char>,
+    pub elements: HashMap<char, [Vec<Direction>; 4]>,
+    pub teleports: HashMap<Position, Position>,
+}
+
+/// How each optical element redirects a beam, given by the directions
+/// it leaves in for a beam arriving travelling Up, Right, Down and Left.
+/// Any other letter is a teleport and is paired up separately in parse_input,
+/// so `v` is always a valve and can't be used as a teleport.
+pub fn element_table() -> HashMap<char, [Vec<Direction>; 4]> {
+    use Direction::*;
+
+    HashMap::from([
+        // empty space
+        ('.', [vec![Up], vec![Right], vec![Down], vec![Left]]),
+        // mirrors
+        ('/', [vec![Right], vec![Up], vec![Left], vec![Down]]),
+        ('\\', [vec![Left], vec![Down], vec![Right], vec![Up]]),
+        // splitters
+        (
+            '-',
+            [
+                vec![Left, Right],
+                vec![Right],
+                vec![Left, Right],
+                vec![Left],
+            ],
+        ),
+        ('|', [vec![Up], vec![Up, Down], vec![Down], vec![Up, Down]]),
+        ('+', std::array::from_fn(|_| vec![Up, Right, Down, Left])),
+        // absorber
+        ('#', [vec![], vec![], vec![], vec![]]),
+        // one-way valves, which absorb beams going the wrong way
+        ('^', [vec![Up], vec![], vec![], vec![]]),
+        ('>', [vec![], vec![Right], vec![], vec![]]),
+        ('v', [vec![], vec![], vec![Down], vec![]]),
+        ('<', [vec![], vec![], vec![], vec![Left]]),
+    ])
+}
+
+/// A beam on a tile, travelling in some direction.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Beam {
+    pub p: Position,
+    pub d: Direction,
+    /// Whether the beam has just come out of a teleport. It then carries on
+    /// from there, where a beam that walked onto the same tile would jump
+    /// back to the other end.
+    pub teleported: bool,
+}
+
+/// How many distinct beams there can be on one tile.
+pub const BEAMS_PER_TILE: usize = 8;
+
+pub fn beam(p: Position, d: Direction) -> Beam {
+    Beam {
+        p,
+        d,
+        teleported: false,
+    }
+}
+
+impl Beam {
+    /// A dense index for the beam, from 0 to BEAMS_PER_TILE times the number of tiles.
+    pub fn id<T>(&self, g: &Grid<T>) -> usize {
+        let tile = g.pos_to_vec_index(&self.p).unwrap();
+        (tile * BEAMS_PER_TILE) + (self.d.index() * 2) + self.teleported as usize
+    }
+
+    pub fn from_id<T>(id: usize, g: &Grid<T>) -> Beam {
+        let tile = id / BEAMS_PER_TILE;
+        Beam {
+            p: pos((tile % g.width) as i64, (tile / g.width) as i64),
+            d: DIRECTIONS[(id % BEAMS_PER_TILE) / 2],
+            teleported: id % 2 == 1,
+        }
+    }
+}
+
+pub fn succ(input: &Input, b: Beam) -> Vec<Beam> {
+    // A beam entering a teleport comes out of the matching one,
+    // which is energised too, and carries on from there.
+    if !b.teleported {
+        if let Some(partner) = input.teleports.get(&b.p) {
+            return vec![Beam {
+                p: *partner,
+                d: b.d,
+                teleported: true,
+            }];
+        }
+    }
+
+    let next_dirs = if b.teleported {
+        std::slice::from_ref(&b.d)
+    } else {
+        let val = input.g.get_pos(&b.p);
+        &input.elements[val][b.d.index()][..]
+    };
+
+    next_dirs
+        .iter()
+        .filter_map(|next_d| {
+            let new_p = b.p.move_in_direction(*next_d);
+            input
+                .g
+                .try_get_pos(&new_p)
+                .is_some()
+                .then_some(beam(new_p, *next_d))
+        })
+        .collect()
+}
+
+/// Every beam that comes from one starting at p going in direction d.
+pub fn follow_beams(input: &Input, p: Position, d: Direction) -> HashSet<Beam> {
+    flood_fill(beam(p, d), |b| succ(input, *b))
+}
+
+pub fn count_energized(input: &Input, p: Position, d: Direction) -> usize {
+    let seen = follow_beams(input, p, d);
+    let energized = seen.iter().map(|b| b.p).collect::<HashSet<_>>();
+
+    energized.len()
+}
+
+fn flood_fill<T, F>(start: T, succ: F) -> HashSet<T>
+where
+    T: Eq + Hash + Copy,
+    F: Fn(&T) -> Vec<T>,
+{
+    let mut stack = vec![start];
+    let mut seen = HashSet::from([start]);
+
+    while let Some(elem) = stack.pop() {
+        let neighbours = succ(&elem);
+        for n in neighbours {
+            if seen.insert(n) {
+                stack.push(n);
+            }
+        }
+    }
+
+    seen
+}
+
+pub fn parse_input(s: &str) -> Input {
+    let lines = s.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+    let g = Grid::from_strings(&lines);
+    let elements = element_table();
+
+    let mut letters = HashMap::<char, Vec<Position>>::new();
+    for y in 0..g.height() {
+        for x in 0..g.width {
+            let c = *g.get(x, y);
+            if c.is_ascii_alphabetic() && !elements.contains_key(&c) {
+                letters.entry(c).or_default().push(pos(x as i64, y as i64));
+            } else if !elements.contains_key(&c) {
+                panic!("unhandled element {}", c);
+            }
+        }
+    }
+
+    let mut teleports = HashMap::new();
+    for (c, ends) in letters {
+        match ends[..] {
+            [a, b] => {
+                teleports.insert(a, b);
+                teleports.insert(b, a);
+            }
+            _ => panic!(
+                "teleport {} needs exactly two ends, found {}",
+                c,
+                ends.len()
+            ),
+        }
+    }
+
+    Input {
+        g,
+        elements,
+        teleports,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    pub width: usize,
+    pub vec: Vec<T>,
+}
+
+impl Grid<char> {
+    pub fn from_strings(lines: &[String]) -> Self {
+        if lines.is_empty() {
+            return Grid {
+                width: 0,
+                vec: vec![],
+            };
+        }
+
+        let width = lines[0].len();
+        let vec = lines.iter().flat_map(|l| l.chars()).collect();
+        Grid { width, vec }
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, val: T) -> Self {
+        Grid {
+            width,
+            vec: vec![val; width * height],
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn height(&self) -> usize {
+        self.vec.len() / self.width
+    }
+
+    fn to_vec_index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height() {
+            Some((y * self.width) + x)
+        } else {
+            None
+        }
+    }
+
+    fn signed_to_vec_index(&self, x: i64, y: i64) -> Option<usize> {
+        let x2 = usize::try_from(x).ok()?;
+        let y2 = usize::try_from(y).ok()?;
+        self.to_vec_index(x2, y2)
+    }
+
+    pub fn pos_to_vec_index(&self, pos: &Position) -> Option<usize> {
+        self.signed_to_vec_index(pos.x, pos.y)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> &T {
+        &self.vec[self.to_vec_index(x, y).unwrap()]
+    }
+
+    pub fn get_pos(&self, pos: &Position) -> &T {
+        self.get(pos.x.try_into().unwrap(), pos.y.try_into().unwrap())
+    }
+
+    pub fn get_pos_mut(&mut self, pos: &Position) -> &mut T {
+        let i = self.pos_to_vec_index(pos).unwrap();
+        &mut self.vec[i]
+    }
+
+    pub fn try_get_pos(&self, pos: &Position) -> Option<&T> {
+        self.pos_to_vec_index(pos).map(|i| &self.vec[i])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Position {
+    pub x: i64,
+    pub y: i64,
+}
+
+pub fn pos(x: i64, y: i64) -> Position {
+    Position { x, y }
+}
+
+impl Position {
+    pub fn move_in_direction(&self, direction: Direction) -> Position {
+        match direction {
+            Direction::Up => Position {
+                x: self.x,
+                y: self.y - 1,
+            },
+            Direction::Right => Position {
+                x: self.x + 1,
+                y: self.y,
+            },
+            Direction::Down => Position {
+                x: self.x,
+                y: self.y + 1,
+            },
+            Direction::Left => Position {
+                x: self.x - 1,
+                y: self.y,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub fn index(&self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Right => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+        }
+    }
+
+    pub fn bit(&self) -> u8 {
+        match self {
+            Direction::Up => 1,
+            Direction::Right => 2,
+            Direction::Down => 4,
+            Direction::Left => 8,
+        }
+    }
+
+    /// Drawn for a beam, so it can't be mistaken for a valve.
+    pub fn arrow(&self) -> char {
+        match self {
+            Direction::Up => '↑',
+            Direction::Right => '→',
+            Direction::Down => '↓',
+            Direction::Left => '←',
+        }
+    }
+}
+
+pub const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_succ() {
+        let input_str = "\
+.|...\\....
+|.-.\\.....
+.....|-...
+........|.
+..........
+.........\\
+..../.\\\\..
+.-.-/..|..
+.|....-|.\\
+..//.|....
+";
+        let input = parse_input(input_str);
+        assert_eq!(
+            succ(&input, beam(pos(5, 0), Direction::Right)),
+            vec![beam(pos(5, 1), Direction::Down)]
+        );
+
+        assert_eq!(
+            succ(&input, beam(pos(4, 1), Direction::Down)),
+            vec![beam(pos(5, 1), Direction::Right)]
+        );
+    }
+
+    #[test]
+    fn test_succ_variant_elements() {
+        let input_str = "\
+.a..#
+..>..
+a.+..
+";
+        let input = parse_input(input_str);
+
+        assert_eq!(succ(&input, beam(pos(4, 0), Direction::Right)), vec![]);
+        assert_eq!(
+            succ(&input, beam(pos(2, 1), Direction::Right)),
+            vec![beam(pos(3, 1), Direction::Right)]
+        );
+        assert_eq!(succ(&input, beam(pos(2, 1), Direction::Up)), vec![]);
+
+        let teleported = Beam {
+            p: pos(0, 2),
+            d: Direction::Up,
+            teleported: true,
+        };
+        assert_eq!(
+            succ(&input, beam(pos(1, 0), Direction::Up)),
+            vec![teleported]
+        );
+        assert_eq!(
+            succ(&input, teleported),
+            vec![beam(pos(0, 1), Direction::Up)]
+        );
+    }
+
+    #[test]
+    fn test_teleport_partner_energised() {
+        // the beam starts on a teleport, so the only way
+        // it reaches the other end is by jumping there
+        let input = parse_input("a..\n...\n.a.\n");
+        assert_eq!(count_energized(&input, pos(0, 0), Direction::Right), 3);
+    }
+
+    #[test]
+    fn test_beam_id() {
+        let input = parse_input("a..\n...\n.a.\n");
+        for id in 0..(input.g.vec.len() * BEAMS_PER_TILE) {
+            assert_eq!(Beam::from_id(id, &input.g).id(&input.g), id);
+        }
+    }
+}
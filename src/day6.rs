@@ -0,0 +1,343 @@
+//! Boat races for day 6.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Race {
+    pub time: u128,
+    pub distance: u128,
+}
+
+/// The Time: and Distance: lines, one column per race.
+/// Each number keeps its digits as written, leading zeros and all,
+/// so the columns can be run together for part 2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sheet {
+    times: Vec<String>,
+    distances: Vec<String>,
+}
+
+impl Sheet {
+    pub fn parse(s: &str) -> Sheet {
+        let mut lines = s.lines();
+        let mut numbers = || {
+            lines
+                .next()
+                .unwrap()
+                .split_ascii_whitespace()
+                .skip(1)
+                .map(|x| {
+                    assert!(x.bytes().all(|b| b.is_ascii_digit()), "bad number: {}", x);
+                    x.to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+        let times = numbers();
+        let distances = numbers();
+        assert_eq!(times.len(), distances.len(), "columns don't line up");
+
+        Sheet { times, distances }
+    }
+
+    /// One race per column, or None if any of them is too big for a u128.
+    pub fn races(&self) -> Option<Vec<Race>> {
+        self.times
+            .iter()
+            .zip(&self.distances)
+            .map(|(time, distance)| {
+                Some(Race {
+                    time: time.parse().ok()?,
+                    distance: distance.parse().ok()?,
+                })
+            })
+            .collect()
+    }
+
+    /// The single race you get by ignoring the spaces between the columns,
+    /// or None if it's too big for a u128.
+    pub fn kerned(&self) -> Option<Race> {
+        Some(Race {
+            time: self.times.concat().parse().ok()?,
+            distance: self.distances.concat().parse().ok()?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Answers {
+    /// The product of the ways to win each race.
+    pub part1: Option<u128>,
+    /// The ways to win the kerned race.
+    pub part2: Option<u128>,
+}
+
+/// Both answers from the same sheet. Either is None if it doesn't fit in a u128.
+pub fn solve(sheet: &Sheet) -> Answers {
+    let part1 = sheet.races().and_then(|races| {
+        races
+            .iter()
+            .try_fold(1u128, |acc, r| acc.checked_mul(count_ways_to_beat(r)))
+    });
+    let part2 = sheet.kerned().map(|r| count_ways_to_beat(&r));
+
+    Answers { part1, part2 }
+}
+
+/// How many hold times beat the record, i.e. how many whole x in 0..=time
+/// have x * (time - x) > distance.
+///
+/// That's the integers strictly between the roots of
+/// x^2 - time * x + distance = 0, which are (time -/+ sqrt(disc)) / 2
+/// with disc = time^2 - 4 * distance. The winning hold times are symmetric
+/// around time / 2, so we only need the first one.
+/// If time^2 doesn't fit in a u128 we search for the first one instead.
+pub fn count_ways_to_beat(race: &Race) -> u128 {
+    let t = race.time;
+    let Some(square) = t.checked_mul(t) else {
+        return count_ways_to_beat_by_search(race);
+    };
+    let disc = match race
+        .distance
+        .checked_mul(4)
+        .and_then(|d| square.checked_sub(d))
+    {
+        // a single root only ties the record, and no roots never reaches it
+        Some(0) | None => return 0,
+        Some(disc) => disc,
+    };
+
+    let s = disc.isqrt();
+    // first winning hold time is floor((t - sqrt(disc)) / 2) + 1,
+    // and when disc isn't a perfect square sqrt(disc) is strictly between s and s + 1
+    let first = if s * s == disc {
+        (t - s) / 2 + 1
+    } else {
+        (t - s - 1) / 2 + 1
+    };
+
+    (t + 1).saturating_sub(2 * first)
+}
+
+/// The same count for times too big to square, found with a binary search
+/// for the first winning x in the first half. The comparison is made as
+/// x > distance / (time - x), which means the same for whole numbers.
+fn count_ways_to_beat_by_search(race: &Race) -> u128 {
+    let t = race.time;
+    let beats = |x: u128| x > race.distance / (t - x);
+
+    // holding for no time at all never wins, so start from 1
+    let (mut lo, mut hi) = (1, t / 2);
+    if hi == 0 || !beats(hi) {
+        return 0;
+    }
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if beats(mid) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    (t - 2 * lo) + 1
+}
+
+/// Tries every hold time. Only any good for checking the closed form.
+#[cfg(test)]
+fn count_ways_to_beat_brute_force(race: &Race) -> u128 {
+    (0..race.time)
+        .map(|x| {
+            let remaining_time = race.time - x;
+            remaining_time * x
+        })
+        .filter(|x| *x > race.distance)
+        .count() as u128
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let input_str = "\
+Time:      7  15   30
+Distance:  9  40  200
+";
+        let sheet = Sheet::parse(input_str);
+        assert_eq!(sheet.races().unwrap().len(), 3);
+        assert_eq!(
+            sheet.kerned(),
+            Some(Race {
+                time: 71530,
+                distance: 940200
+            })
+        );
+
+        assert_eq!(
+            solve(&sheet),
+            Answers {
+                part1: Some(288),
+                part2: Some(71503)
+            }
+        );
+    }
+
+    #[test]
+    fn test_kerned() {
+        let sheet = Sheet::parse("Time: 10 0 7\nDistance: 5 200 0\n");
+        assert_eq!(
+            sheet.kerned(),
+            Some(Race {
+                time: 1007,
+                distance: 52000
+            })
+        );
+
+        // leading zeros still count as digits
+        let sheet = Sheet::parse("Time: 7 05\nDistance: 9 040\n");
+        assert_eq!(
+            sheet.races(),
+            Some(vec![
+                Race {
+                    time: 7,
+                    distance: 9
+                },
+                Race {
+                    time: 5,
+                    distance: 40
+                }
+            ])
+        );
+        assert_eq!(
+            sheet.kerned(),
+            Some(Race {
+                time: 705,
+                distance: 9040
+            })
+        );
+
+        let sheet = Sheet::parse(&format!("Time: {} 1\nDistance: 1 1\n", u128::MAX / 5));
+        assert_eq!(sheet.kerned(), None);
+        assert_eq!(solve(&sheet).part2, None);
+
+        let sheet = Sheet::parse(&format!("Time: {}0 1\nDistance: 1 1\n", u128::MAX));
+        assert_eq!(solve(&sheet).part1, None);
+    }
+
+    #[test]
+    fn test_sample() {
+        let races = [(7, 9), (15, 40), (30, 200), (71530, 940200)]
+            .map(|(time, distance)| Race { time, distance });
+        let ways = races.map(|r| count_ways_to_beat(&r));
+
+        assert_eq!(ways, [4, 8, 9, 71503]);
+        for r in races {
+            assert_eq!(count_ways_to_beat(&r), count_ways_to_beat_brute_force(&r));
+        }
+    }
+
+    #[test]
+    fn test_real_input_matches_brute_force() {
+        let races = [
+            (35, 212),
+            (93, 2060),
+            (73, 1201),
+            (66, 1044),
+            (35937366, 212206012011044),
+        ]
+        .map(|(time, distance)| Race { time, distance });
+
+        for r in races {
+            assert_eq!(count_ways_to_beat(&r), count_ways_to_beat_brute_force(&r));
+        }
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        // includes plenty of perfect square discriminants and records nobody can beat
+        for time in 0..60 {
+            for distance in 0..(time * time / 4 + 3) {
+                let race = Race { time, distance };
+                assert_eq!(
+                    count_ways_to_beat(&race),
+                    count_ways_to_beat_brute_force(&race),
+                    "{:?}",
+                    race
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_large() {
+        // hold for anything but 0 or the whole time and you win
+        let time = 1 << 63;
+        assert_eq!(count_ways_to_beat(&Race { time, distance: 0 }), time - 1);
+        // only the halfway point beats this
+        let half = time / 2;
+        assert_eq!(
+            count_ways_to_beat(&Race {
+                time,
+                distance: half * half - 1
+            }),
+            1
+        );
+        assert_eq!(
+            count_ways_to_beat(&Race {
+                time: u128::MAX,
+                distance: 1
+            }),
+            u128::MAX - 1
+        );
+    }
+
+    #[test]
+    fn test_search_matches_closed_form() {
+        for time in 0..60 {
+            for distance in 0..(time * time / 4 + 3) {
+                let race = Race { time, distance };
+                assert_eq!(
+                    count_ways_to_beat_by_search(&race),
+                    count_ways_to_beat(&race),
+                    "{:?}",
+                    race
+                );
+            }
+        }
+
+        // either side of the largest time that can still be squared
+        for time in [u64::MAX as u128, u64::MAX as u128 + 1] {
+            for distance in [0, 1 << 100, (time / 2) * (time - time / 2) - 1] {
+                let race = Race { time, distance };
+                assert_eq!(
+                    count_ways_to_beat_by_search(&race),
+                    count_ways_to_beat(&race),
+                    "{:?}",
+                    race
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_time_above_u64() {
+        // time^2 is far too big for a u128 in all of these
+        let time = (1 << 66) + 5;
+        let race = Race {
+            time,
+            distance: (3 << 100) + 7,
+        };
+        assert_eq!(count_ways_to_beat(&race), 73786976191758991292);
+
+        let race = Race {
+            time: (1 << 127) + 3,
+            distance: u128::MAX,
+        };
+        assert_eq!(count_ways_to_beat(&race), 1 << 127);
+
+        let race = Race {
+            time: u128::MAX,
+            distance: u128::MAX,
+        };
+        assert_eq!(count_ways_to_beat(&race), u128::MAX - 3);
+    }
+}
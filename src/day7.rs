@@ -0,0 +1,446 @@
+use std::cmp::{Ordering, Reverse};
+
+#[derive(Debug, Clone)]
+pub struct Hand {
+    pub cards: String,
+    pub bid: i64,
+}
+
+pub fn parse_hand(line: &str) -> Hand {
+    let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
+    Hand {
+        cards: parts[0].to_string(),
+        bid: parts[1].parse().unwrap(),
+    }
+}
+
+/// Strongest first, so sorting puts the best hands at the front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HandType {
+    Five,
+    Four,
+    FullHouse,
+    Three,
+    TwoPair,
+    OnePair,
+    HighCard,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Every card, weakest first.
+    card_order: Vec<char>,
+    /// Cards that count as whatever makes the hand strongest.
+    /// They're still ranked by card_order when breaking ties.
+    wildcards: Vec<char>,
+    hand_size: usize,
+}
+
+/// A hand's place in the final standings, counting from 1 for the weakest.
+#[derive(Debug, Clone)]
+pub struct RankedHand {
+    pub rank: usize,
+    pub hand: Hand,
+    pub hand_type: HandType,
+    /// What the wildcards in the hand became, if it has any.
+    pub substitution: Option<char>,
+    pub winnings: i64,
+}
+
+impl Rules {
+    pub fn new(card_order: &str, wildcards: &str, hand_size: usize) -> Rules {
+        Rules {
+            card_order: card_order.chars().collect(),
+            wildcards: wildcards.chars().collect(),
+            hand_size,
+        }
+    }
+
+    /// The rules for part 1.
+    pub fn standard() -> Rules {
+        Rules::new("23456789TJQKA", "", 5)
+    }
+
+    /// The rules for part 2, where J is the weakest card but can be anything.
+    pub fn jokers() -> Rules {
+        Rules::new("J23456789TQKA", "J", 5)
+    }
+
+    fn card_strength(&self, c: char) -> usize {
+        self.card_order
+            .iter()
+            .position(|x| *x == c)
+            .expect("bad card")
+    }
+
+    /// How many of each card the hand has, most first,
+    /// with the wildcards added to the biggest group.
+    pub fn signature(&self, cards: &str) -> Vec<usize> {
+        assert_eq!(cards.chars().count(), self.hand_size, "bad hand size");
+
+        let mut counts = vec![0; self.card_order.len()];
+        let mut wild = 0;
+        for c in cards.chars() {
+            if self.wildcards.contains(&c) {
+                wild += 1;
+            } else {
+                counts[self.card_strength(c)] += 1;
+            }
+        }
+
+        let mut signature = counts.into_iter().filter(|n| *n > 0).collect::<Vec<_>>();
+        signature.sort_by(|a, b| b.cmp(a));
+        match signature.first_mut() {
+            Some(n) => *n += wild,
+            None => signature.push(wild),
+        }
+        signature
+    }
+
+    pub fn hand_type(&self, cards: &str) -> HandType {
+        let signature = self.signature(cards);
+        let first = signature[0];
+        let second = signature.get(1).copied().unwrap_or(0);
+
+        match (first, second) {
+            (5.., _) => HandType::Five,
+            (4, _) => HandType::Four,
+            (3, 2..) => HandType::FullHouse,
+            (3, _) => HandType::Three,
+            (2, 2..) => HandType::TwoPair,
+            (2, _) => HandType::OnePair,
+            _ => HandType::HighCard,
+        }
+    }
+
+    /// The card the wildcards should all pretend to be to get the best hand type,
+    /// or None if there aren't any wildcards.
+    /// That's whichever other card the hand has most of (the strongest if there's a tie),
+    /// or the strongest card there is if the hand is all wildcards.
+    pub fn best_substitution(&self, cards: &str) -> Option<char> {
+        if !cards.chars().any(|c| self.wildcards.contains(&c)) {
+            return None;
+        }
+
+        let natural = cards
+            .chars()
+            .filter(|c| !self.wildcards.contains(c))
+            .collect::<Vec<_>>();
+        natural
+            .iter()
+            .copied()
+            .max_by_key(|c| {
+                let count = natural.iter().filter(|x| *x == c).count();
+                (count, self.card_strength(*c))
+            })
+            .or_else(|| {
+                self.card_order
+                    .iter()
+                    .copied()
+                    .rfind(|c| !self.wildcards.contains(c))
+            })
+    }
+
+    /// What hands are sorted by, weakest first: the type, then card by card.
+    pub fn strength(&self, cards: &str) -> (Reverse<HandType>, Vec<usize>) {
+        let cards_strength = cards.chars().map(|c| self.card_strength(c)).collect();
+        (Reverse(self.hand_type(cards)), cards_strength)
+    }
+
+    /// Orders hands weakest first: by type, then card by card.
+    pub fn cmp_hands(&self, a: &str, b: &str) -> Ordering {
+        self.strength(a).cmp(&self.strength(b))
+    }
+
+    /// Puts the hands in order, weakest first, and works out what each one wins.
+    /// Each hand is only classified once, however many times the sort compares it.
+    pub fn rank(&self, hands: &[Hand]) -> Vec<RankedHand> {
+        let mut keyed = hands
+            .iter()
+            .map(|h| (self.strength(&h.cards), h.clone()))
+            .collect::<Vec<_>>();
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        keyed
+            .into_iter()
+            .enumerate()
+            .map(|(i, ((Reverse(hand_type), _), hand))| RankedHand {
+                rank: i + 1,
+                hand_type,
+                substitution: self.best_substitution(&hand.cards),
+                winnings: hand.bid * (i as i64 + 1),
+                hand,
+            })
+            .collect()
+    }
+}
+
+pub fn total_winnings(ranked: &[RankedHand]) -> i64 {
+    ranked.iter().map(|r| r.winnings).sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Rank,
+    /// Alphabetical by the cards as written.
+    Hand,
+    /// By hand type, then rank within each type.
+    Type,
+    Bid,
+    Winnings,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableOptions {
+    pub csv: bool,
+    pub sort: SortKey,
+    pub descending: bool,
+}
+
+impl TableOptions {
+    /// Reads [--table] [--csv] [--sort rank|hand|type|bid|winnings] [--desc].
+    /// None if the table wasn't asked for at all.
+    pub fn from_args(args: &[String]) -> Result<Option<TableOptions>, String> {
+        if !args.iter().any(|a| a == "--table" || a == "--csv") {
+            return Ok(None);
+        }
+
+        let mut options = TableOptions {
+            csv: false,
+            sort: SortKey::Rank,
+            descending: false,
+        };
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--table" => (),
+                "--csv" => options.csv = true,
+                "--desc" => options.descending = true,
+                "--sort" => {
+                    options.sort = match args.get(i + 1).map(|k| k.as_str()) {
+                        Some("rank") => SortKey::Rank,
+                        Some("hand") => SortKey::Hand,
+                        Some("type") => SortKey::Type,
+                        Some("bid") => SortKey::Bid,
+                        Some("winnings") => SortKey::Winnings,
+                        Some(k) => return Err(format!("unknown sort key: {}", k)),
+                        None => return Err("--sort needs a key".to_string()),
+                    };
+                    i += 1;
+                }
+                a => return Err(format!("unknown option: {}", a)),
+            }
+            i += 1;
+        }
+        Ok(Some(options))
+    }
+}
+
+/// The standings as a table, one row per hand, as aligned text or CSV.
+pub fn render_table(ranked: &[RankedHand], options: &TableOptions) -> String {
+    let mut rows = ranked.iter().collect::<Vec<_>>();
+    rows.sort_by(|a, b| {
+        let ordering = match options.sort {
+            SortKey::Rank => a.rank.cmp(&b.rank),
+            SortKey::Hand => a.hand.cards.cmp(&b.hand.cards),
+            // strongest type last, to match rank
+            SortKey::Type => b.hand_type.cmp(&a.hand_type).then(a.rank.cmp(&b.rank)),
+            SortKey::Bid => a.hand.bid.cmp(&b.hand.bid),
+            SortKey::Winnings => a.winnings.cmp(&b.winnings),
+        };
+        if options.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    let header = ["rank", "hand", "type", "substitution", "bid", "winnings"].map(String::from);
+    let cells = rows
+        .iter()
+        .map(|r| {
+            [
+                r.rank.to_string(),
+                r.hand.cards.clone(),
+                format!("{:?}", r.hand_type),
+                r.substitution.map_or("-".to_string(), |c| c.to_string()),
+                r.hand.bid.to_string(),
+                r.winnings.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    if options.csv {
+        for row in std::iter::once(&header).chain(&cells) {
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        return out;
+    }
+
+    let widths = (0..header.len())
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(&cells)
+                .map(|row| row[i].len())
+                .max()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    for row in std::iter::once(&header).chain(&cells) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, w)| format!("{:<w$}", cell, w = w))
+            .collect::<Vec<_>>()
+            .join("  ");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_winnings() {
+        let input_str = "\
+32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483
+";
+        let hands = input_str.lines().map(parse_hand).collect::<Vec<_>>();
+
+        let ranked = Rules::standard().rank(&hands);
+        assert_eq!(total_winnings(&ranked), 6440);
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|r| r.hand.cards.as_str())
+                .collect::<Vec<_>>(),
+            vec!["32T3K", "KTJJT", "KK677", "T55J5", "QQQJA"]
+        );
+
+        let ranked = Rules::jokers().rank(&hands);
+        assert_eq!(total_winnings(&ranked), 5905);
+        assert_eq!(ranked[4].hand.cards, "KTJJT");
+        assert_eq!(ranked[4].hand_type, HandType::Four);
+        assert_eq!(ranked[4].winnings, 220 * 5);
+    }
+
+    #[test]
+    fn test_hand_type() {
+        let rules = Rules::standard();
+        assert_eq!(rules.signature("KTJJT"), vec![2, 2, 1]);
+        assert_eq!(rules.hand_type("KTJJT"), HandType::TwoPair);
+        assert_eq!(rules.hand_type("23332"), HandType::FullHouse);
+        assert_eq!(rules.hand_type("23456"), HandType::HighCard);
+
+        let rules = Rules::jokers();
+        assert_eq!(rules.signature("KTJJT"), vec![4, 1]);
+        assert_eq!(rules.signature("JJJJJ"), vec![5]);
+        assert_eq!(rules.hand_type("JJJJJ"), HandType::Five);
+        assert_eq!(rules.hand_type("2J3J4"), HandType::Three);
+        assert_eq!(rules.hand_type("22J33"), HandType::FullHouse);
+
+        // two kinds of wildcard, and hands of three cards
+        let rules = Rules::new("2345*?", "*?", 3);
+        assert_eq!(rules.hand_type("2*?"), HandType::Three);
+        assert_eq!(rules.hand_type("234"), HandType::HighCard);
+        assert_eq!(rules.hand_type("2*4"), HandType::OnePair);
+    }
+
+    #[test]
+    fn test_hand_type_matches_substitution() {
+        // the old way: try every card in place of all the jokers
+        let rules = Rules::jokers();
+        let standard = Rules::standard();
+        let by_substitution = |cards: &str| {
+            "23456789TQKA"
+                .chars()
+                .map(|c| standard.hand_type(&cards.replace('J', &c.to_string())))
+                .min()
+                .unwrap()
+        };
+
+        let cards = ['2', '7', 'J', 'K'];
+        for i in 0..cards.len().pow(5) {
+            let hand = (0..5)
+                .map(|k| cards[(i / cards.len().pow(k)) % cards.len()])
+                .collect::<String>();
+            assert_eq!(rules.hand_type(&hand), by_substitution(&hand), "{}", hand);
+        }
+    }
+
+    #[test]
+    fn test_best_substitution() {
+        let rules = Rules::jokers();
+        assert_eq!(rules.best_substitution("32T3K"), None);
+        assert_eq!(rules.best_substitution("KTJJT"), Some('T'));
+        assert_eq!(rules.best_substitution("2J3K4"), Some('K'));
+        assert_eq!(rules.best_substitution("JJJJJ"), Some('A'));
+
+        for cards in ["KTJJT", "2J3K4", "JJJJJ", "QQQJA"] {
+            let substituted =
+                cards.replace('J', &rules.best_substitution(cards).unwrap().to_string());
+            assert_eq!(
+                Rules::standard().hand_type(&substituted),
+                rules.hand_type(cards)
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_table() {
+        let input_str = "\
+32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483
+";
+        let hands = input_str.lines().map(parse_hand).collect::<Vec<_>>();
+        let ranked = Rules::jokers().rank(&hands);
+
+        let options = TableOptions::from_args(&["--csv".to_string()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            render_table(&ranked, &options),
+            "\
+rank,hand,type,substitution,bid,winnings
+1,32T3K,OnePair,-,765,765
+2,KK677,TwoPair,-,28,56
+3,T55J5,Four,5,684,2052
+4,QQQJA,Four,Q,483,1932
+5,KTJJT,Four,T,220,1100
+"
+        );
+
+        let args = ["--table", "--sort", "winnings", "--desc"].map(String::from);
+        let options = TableOptions::from_args(&args).unwrap().unwrap();
+        assert_eq!(
+            render_table(&ranked[..2], &options),
+            "\
+rank  hand   type     substitution  bid  winnings
+1     32T3K  OnePair  -             765  765
+2     KK677  TwoPair  -             28   56
+"
+        );
+
+        assert_eq!(TableOptions::from_args(&[]), Ok(None));
+        assert_eq!(
+            TableOptions::from_args(&["--table", "--sort"].map(String::from)),
+            Err("--sort needs a key".to_string())
+        );
+        assert_eq!(
+            TableOptions::from_args(&["--csv", "--sort", "suit"].map(String::from)),
+            Err("unknown sort key: suit".to_string())
+        );
+    }
+}
@@ -0,0 +1,611 @@
+//! Ghost paths through the day 8 network.
+//!
+//! Each ghost's state is its node plus where it is in the instructions,
+//! so it must eventually repeat. Once we know when each ghost is on a Z node
+//! before and during its cycle, the earliest time they all are at once
+//! falls out of the Chinese remainder theorem,
+//! unless they're on Z nodes so often that scanning for it is quicker.
+
+use std::collections::{HashMap, HashSet};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Left,
+    Right,
+}
+
+lazy_static! {
+    static ref NODE_REGEX: Regex = Regex::new(r"^(\w+) = \((\w+), (\w+)\)").unwrap();
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub left: String,
+    pub right: String,
+}
+
+pub fn parse_node(line: &str) -> Node {
+    let captures = NODE_REGEX.captures(line).unwrap();
+
+    Node {
+        name: captures[1].to_string(),
+        left: captures[2].to_string(),
+        right: captures[3].to_string(),
+    }
+}
+
+pub fn parse_instructions(line: &str) -> Vec<Instruction> {
+    line.chars()
+        .map(|c| match c {
+            'L' => Instruction::Left,
+            'R' => Instruction::Right,
+            _ => panic!("invalid instruction"),
+        })
+        .collect()
+}
+
+pub fn is_goal(name: &str) -> bool {
+    name.ends_with('Z')
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// A node leads to a node that's never defined.
+    UndefinedReference {
+        node: String,
+        target: String,
+    },
+    DuplicateDefinition(String),
+    /// There's no path at all from this A node to any Z node,
+    /// whatever the instructions say.
+    NoReachableGoal(String),
+    /// At least one way out of the node leads straight back to it.
+    SelfLoop(String),
+    NoInstructions,
+}
+
+impl Diagnostic {
+    /// Whether the network can't be followed at all.
+    /// The others are worth knowing about but don't stop us.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Diagnostic::UndefinedReference { .. }
+                | Diagnostic::DuplicateDefinition(_)
+                | Diagnostic::NoInstructions
+        )
+    }
+}
+
+/// Everything that looks wrong with the node list.
+pub fn diagnose(nodes: &[Node]) -> Vec<Diagnostic> {
+    let mut out = vec![];
+
+    let mut defined = HashSet::new();
+    for n in nodes {
+        if !defined.insert(n.name.as_str()) {
+            out.push(Diagnostic::DuplicateDefinition(n.name.clone()));
+        }
+    }
+
+    for n in nodes {
+        for target in [&n.left, &n.right] {
+            if !defined.contains(target.as_str()) {
+                out.push(Diagnostic::UndefinedReference {
+                    node: n.name.clone(),
+                    target: target.clone(),
+                });
+            }
+        }
+        if n.left == n.name || n.right == n.name {
+            out.push(Diagnostic::SelfLoop(n.name.clone()));
+        }
+    }
+
+    // go with the first definition of any duplicates
+    let mut nodes_lookup = HashMap::new();
+    for n in nodes {
+        nodes_lookup.entry(n.name.as_str()).or_insert(n);
+    }
+    for start in nodes.iter().filter(|n| n.name.ends_with('A')) {
+        let mut seen = HashSet::from([start.name.as_str()]);
+        let mut stack = vec![start.name.as_str()];
+        while let Some(curr) = stack.pop() {
+            // undefined nodes have already been reported, so they're dead ends here
+            if let Some(node) = nodes_lookup.get(curr) {
+                for next in [node.left.as_str(), node.right.as_str()] {
+                    if seen.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        if !seen.iter().any(|n| is_goal(n)) {
+            out.push(Diagnostic::NoReachableGoal(start.name.clone()));
+        }
+    }
+
+    out
+}
+
+/// The nodes and instructions compiled down to integer IDs,
+/// so following them doesn't need any string lookups.
+#[derive(Debug)]
+pub struct Network {
+    pub instructions: Vec<Instruction>,
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    left: Vec<usize>,
+    right: Vec<usize>,
+    /// pass_jumps[k][node] is where you end up from node
+    /// after 2^k full passes through the instructions.
+    pass_jumps: Vec<Vec<usize>>,
+}
+
+impl Network {
+    /// Fails with the fatal diagnostics if the network can't be followed.
+    pub fn new(instructions: Vec<Instruction>, nodes: &[Node]) -> Result<Network, Vec<Diagnostic>> {
+        let mut fatal = diagnose(nodes)
+            .into_iter()
+            .filter(|d| d.is_fatal())
+            .collect::<Vec<_>>();
+        if instructions.is_empty() {
+            fatal.push(Diagnostic::NoInstructions);
+        }
+        if !fatal.is_empty() {
+            return Err(fatal);
+        }
+
+        let names = nodes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+        let ids = names
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.clone(), i))
+            .collect::<HashMap<_, _>>();
+        let left = nodes.iter().map(|n| ids[&n.left]).collect();
+        let right = nodes.iter().map(|n| ids[&n.right]).collect();
+
+        let mut network = Network {
+            instructions,
+            names,
+            ids,
+            left,
+            right,
+            pass_jumps: vec![],
+        };
+
+        let one_pass = (0..network.len())
+            .map(|node| {
+                (0..network.instructions.len()).fold(node, |curr, idx| network.step(curr, idx))
+            })
+            .collect::<Vec<_>>();
+        network.pass_jumps.push(one_pass);
+        for k in 1..u64::BITS as usize {
+            let prev = &network.pass_jumps[k - 1];
+            let next = prev.iter().map(|n| prev[*n]).collect();
+            network.pass_jumps.push(next);
+        }
+
+        Ok(network)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, node: usize) -> &str {
+        &self.names[node]
+    }
+
+    pub fn is_goal(&self, node: usize) -> bool {
+        is_goal(&self.names[node])
+    }
+
+    /// Where you go from node following instruction idx.
+    pub fn step(&self, node: usize, idx: usize) -> usize {
+        match self.instructions[idx] {
+            Instruction::Left => self.left[node],
+            Instruction::Right => self.right[node],
+        }
+    }
+
+    /// Where you are after the given number of steps from node,
+    /// starting at the beginning of the instructions.
+    /// Whole passes through the instructions use the jump tables,
+    /// so this takes O(log(steps) + instructions) time.
+    pub fn position_after(&self, node: usize, steps: u64) -> usize {
+        let len = self.instructions.len() as u64;
+        let passes = steps / len;
+
+        let mut curr = (0..u64::BITS as usize)
+            .filter(|k| passes & (1 << k) != 0)
+            .fold(node, |curr, k| self.pass_jumps[k][curr]);
+        for idx in 0..(steps % len) as usize {
+            curr = self.step(curr, idx);
+        }
+        curr
+    }
+}
+
+/// When a ghost is on a Z node, in steps from its start.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GhostCycle {
+    /// Steps before the ghost's state starts repeating.
+    pub preamble: u64,
+    pub cycle_len: u64,
+    /// Z hits before the cycle starts. These never happen again.
+    pub early_hits: Vec<u64>,
+    /// Z hits during the first time round the cycle,
+    /// each one repeating every cycle_len steps after that.
+    pub cycle_hits: Vec<u64>,
+}
+
+impl GhostCycle {
+    pub fn is_hit(&self, step: u64) -> bool {
+        if step < self.preamble {
+            return self.early_hits.contains(&step);
+        }
+        let offset = (step - self.preamble) % self.cycle_len;
+        self.cycle_hits.contains(&(self.preamble + offset))
+    }
+}
+
+/// Follows a ghost from start until its (node, instruction index) state repeats.
+pub fn find_cycle(network: &Network, start: usize) -> GhostCycle {
+    let len = network.instructions.len();
+    let mut first_seen = vec![None; network.len() * len];
+    let mut hits = vec![];
+    let mut curr = start;
+    let mut step: u64 = 0;
+
+    loop {
+        let idx = (step % len as u64) as usize;
+        if let Some(preamble) = first_seen[curr * len + idx] {
+            let (early_hits, cycle_hits) = hits.iter().partition(|h| **h < preamble);
+            return GhostCycle {
+                preamble,
+                cycle_len: step - preamble,
+                early_hits,
+                cycle_hits,
+            };
+        }
+        first_seen[curr * len + idx] = Some(step);
+
+        if network.is_goal(curr) {
+            hits.push(step);
+        }
+
+        curr = network.step(curr, idx);
+        step += 1;
+    }
+}
+
+/// The first step at which every ghost is on a Z node at the same time,
+/// or None if that never happens.
+pub fn earliest_arrival(ghosts: &[GhostCycle]) -> Option<u64> {
+    // Before every ghost is in its cycle, just check each step
+    // the first ghost could be on a Z node.
+    let settled = ghosts.iter().map(|g| g.preamble).max()?;
+    let first = &ghosts[0];
+    if let Some(step) = (0..settled)
+        .filter(|s| first.is_hit(*s))
+        .find(|s| ghosts.iter().all(|g| g.is_hit(*s)))
+    {
+        return Some(step);
+    }
+
+    // After that, each ghost is on a Z node when the step is one of
+    // a few residues modulo its cycle length, so combine them.
+    let mut congruences = vec![(0, 1)];
+    for g in ghosts {
+        // With lots of hits per cycle the combinations multiply out,
+        // but then there are so many hits that a plain scan finds one quickly.
+        if congruences.len() * g.cycle_hits.len() > MAX_CONGRUENCES {
+            return scan_arrival(ghosts, settled);
+        }
+
+        let m = g.cycle_len as i128;
+        congruences = congruences
+            .iter()
+            .flat_map(|(a, n)| {
+                g.cycle_hits
+                    .iter()
+                    .filter_map(move |h| crt((*a, *n), (*h as i128 % m, m)))
+            })
+            .collect();
+        congruences.sort();
+        congruences.dedup();
+    }
+
+    congruences
+        .iter()
+        .map(|(a, n)| {
+            // the smallest step that's at least settled and is a mod n
+            let settled = settled as i128;
+            a + ((settled - a).max(0) + n - 1) / n * n
+        })
+        .min()
+        .and_then(|step| u64::try_from(step).ok())
+}
+
+/// More residue combinations than this and we give up on the CRT and scan instead.
+const MAX_CONGRUENCES: usize = 1 << 12;
+
+/// Checks each step from settled onwards that the ghost with the longest cycle
+/// is on a Z node, until every ghost's cycle has come round at the same time.
+fn scan_arrival(ghosts: &[GhostCycle], settled: u64) -> Option<u64> {
+    let longest = ghosts.iter().max_by_key(|g| g.cycle_len)?;
+    let period = ghosts.iter().try_fold(1u128, |acc, g| {
+        let n = g.cycle_len as u128;
+        (acc / gcd(acc, n)).checked_mul(n)
+    });
+    let end = period
+        .and_then(|p| u64::try_from(settled as u128 + p).ok())
+        .unwrap_or(u64::MAX);
+
+    // the start of the time round the cycle that settled falls in
+    let mut round = settled - ((settled - longest.preamble) % longest.cycle_len);
+    while round < end {
+        for h in &longest.cycle_hits {
+            let step = round.checked_add(h - longest.preamble)?;
+            if step >= settled && step < end && ghosts.iter().all(|g| g.is_hit(step)) {
+                return Some(step);
+            }
+        }
+        round = round.checked_add(longest.cycle_len)?;
+    }
+    None
+}
+
+/// Combines x = a1 (mod n1) and x = a2 (mod n2) into a single congruence
+/// modulo lcm(n1, n2). The moduli don't have to be coprime,
+/// but if they share a factor the residues have to agree on it.
+fn crt((a1, n1): (i128, i128), (a2, n2): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = extended_gcd(n1, n2);
+    if (a2 - a1) % g != 0 {
+        return None;
+    }
+
+    let lcm = n1 / g * n2;
+    // p * n1 = g (mod n2), so this k makes a1 + k * n1 = a2 (mod n2)
+    let k = ((a2 - a1) / g % (n2 / g)) * p % (n2 / g);
+    Some(((a1 + k * n1).rem_euclid(lcm), lcm))
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Returns (g, x, y) with a * x + b * y = g = gcd(a, b).
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_network(s: &str) -> Network {
+        let mut lines = s.lines();
+        let instructions = parse_instructions(lines.next().unwrap());
+        let nodes = lines.skip(1).map(parse_node).collect::<Vec<_>>();
+        Network::new(instructions, &nodes).unwrap()
+    }
+
+    fn ghost_cycles(s: &str) -> Vec<GhostCycle> {
+        let network = parse_network(s);
+        (0..network.len())
+            .filter(|n| network.name(*n).ends_with('A'))
+            .map(|n| find_cycle(&network, n))
+            .collect()
+    }
+
+    #[test]
+    fn test_sample() {
+        let ghosts = ghost_cycles(
+            "\
+LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)
+",
+        );
+
+        assert_eq!(
+            ghosts[0],
+            GhostCycle {
+                preamble: 1,
+                cycle_len: 2,
+                early_hits: vec![],
+                cycle_hits: vec![2],
+            }
+        );
+        assert_eq!(earliest_arrival(&ghosts), Some(6));
+    }
+
+    #[test]
+    fn test_earliest_arrival() {
+        // Z on every odd step, and on steps 2 mod 3.
+        // The LCM of the first hits would say 2.
+        let ghosts = ghost_cycles(
+            "\
+L
+
+1A = (1Z, 1Z)
+1Z = (1A, 1A)
+2A = (2B, 2B)
+2B = (2Z, 2Z)
+2Z = (2C, 2C)
+2C = (2B, 2B)
+",
+        );
+        assert_eq!(earliest_arrival(&ghosts), Some(5));
+
+        // Z only on odd steps, and only on even ones
+        let ghosts = ghost_cycles(
+            "\
+L
+
+1A = (1Z, 1Z)
+1Z = (1A, 1A)
+2A = (2B, 2B)
+2B = (2Z, 2Z)
+2Z = (2B, 2B)
+",
+        );
+        assert_eq!(earliest_arrival(&ghosts), None);
+
+        // the second ghost only passes a Z node on its way into its cycle
+        let ghosts = ghost_cycles(
+            "\
+L
+
+1A = (1Z, 1Z)
+1Z = (1A, 1A)
+2A = (2Z, 2Z)
+2Z = (2B, 2B)
+2B = (2B, 2B)
+",
+        );
+        assert_eq!(ghosts[1].early_hits, vec![1]);
+        assert_eq!(ghosts[1].cycle_hits, vec![]);
+        assert_eq!(earliest_arrival(&ghosts), Some(1));
+    }
+
+    #[test]
+    fn test_earliest_arrival_many_hits() {
+        // Each ghost steps from its A node into a cycle where
+        // every node is a Z node apart from the given ones,
+        // which is far too many hits to combine one by one.
+        let network_str = |misses: &dyn Fn(usize) -> Vec<usize>| {
+            let mut s = "L\n\n".to_string();
+            for (i, len) in [31, 37, 41, 43, 47, 53].into_iter().enumerate() {
+                let name = |j: usize| {
+                    let end = if misses(i).contains(&j) { 'X' } else { 'Z' };
+                    format!("G{}N{}{}", i, j, end)
+                };
+                s += &format!("G{}A = ({}, {})\n", i, name(0), name(0));
+                for j in 0..len {
+                    let next = name((j + 1) % len);
+                    s += &format!("{} = ({}, {})\n", name(j), next, next);
+                }
+            }
+            s
+        };
+
+        let ghosts = ghost_cycles(&network_str(&|_| vec![0]));
+        assert_eq!(ghosts[0].cycle_hits.len(), 30);
+        assert_eq!(earliest_arrival(&ghosts), Some(2));
+
+        // miss a different node in each cycle as well,
+        // so the answer has to come from further round the cycles
+        let ghosts = ghost_cycles(&network_str(&|i| vec![0, i + 1, 2 * i + 3]));
+        let slow = (0..1000000).find(|s| ghosts.iter().all(|g| g.is_hit(*s)));
+        assert!(slow.unwrap() > 2);
+        assert_eq!(earliest_arrival(&ghosts), slow);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt((1, 4), (3, 6)), Some((9, 12)));
+        assert_eq!(crt((1, 4), (2, 6)), None);
+        assert_eq!(crt((0, 1), (4, 7)), Some((4, 7)));
+    }
+
+    #[test]
+    fn test_position_after() {
+        let network = parse_network(
+            "\
+LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)
+",
+        );
+
+        // the slow way, one step at a time
+        let walk = |node: usize, steps: u64| {
+            (0..steps).fold(node, |curr, s| {
+                network.step(curr, (s % network.instructions.len() as u64) as usize)
+            })
+        };
+
+        let start = network.id("AAA").unwrap();
+        for steps in 0..50 {
+            assert_eq!(network.position_after(start, steps), walk(start, steps));
+        }
+        assert_eq!(network.name(network.position_after(start, 1)), "BBB");
+        assert_eq!(network.name(network.position_after(start, 6)), "ZZZ");
+        assert_eq!(
+            network.name(network.position_after(start, 1_000_000_000_000_000)),
+            "ZZZ"
+        );
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let nodes = "\
+AAA = (BBB, CCC)
+BBB = (BBB, DDD)
+CCC = (ZZZ, ZZZ)
+CCC = (AAA, AAA)
+ZZZ = (ZZZ, ZZZ)
+XXA = (XXX, XXX)
+XXX = (XXX, XXA)
+"
+        .lines()
+        .map(parse_node)
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnose(&nodes),
+            vec![
+                Diagnostic::DuplicateDefinition("CCC".to_string()),
+                Diagnostic::UndefinedReference {
+                    node: "BBB".to_string(),
+                    target: "DDD".to_string()
+                },
+                Diagnostic::SelfLoop("BBB".to_string()),
+                Diagnostic::SelfLoop("ZZZ".to_string()),
+                Diagnostic::SelfLoop("XXX".to_string()),
+                Diagnostic::NoReachableGoal("XXA".to_string()),
+            ]
+        );
+
+        let fatal = Network::new(vec![Instruction::Left], &nodes).unwrap_err();
+        assert_eq!(fatal.len(), 2);
+        assert_eq!(
+            Network::new(vec![], &nodes[4..]).unwrap_err(),
+            vec![Diagnostic::NoInstructions]
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use std::{
+    fmt,
+    iter::successors,
+    num::ParseIntError,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+/// A fraction in lowest terms with a positive denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    pub fn new(num: i128, den: i128) -> Rational {
+        assert_ne!(den, 0, "zero denominator");
+        let g = gcd(num, den).max(1) * den.signum();
+        Rational {
+            num: num / g,
+            den: den / g,
+        }
+    }
+
+    pub fn integer(n: i128) -> Rational {
+        Rational { num: n, den: 1 }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl Add for Rational {
+    type Output = Rational;
+    fn add(self, rhs: Rational) -> Rational {
+        Rational::new(self.num * rhs.den + rhs.num * self.den, self.den * rhs.den)
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+    fn sub(self, rhs: Rational) -> Rational {
+        self + -rhs
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+    fn neg(self) -> Rational {
+        Rational {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+    fn mul(self, rhs: Rational) -> Rational {
+        Rational::new(self.num * rhs.num, self.den * rhs.den)
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+    fn div(self, rhs: Rational) -> Rational {
+        Rational::new(self.num * rhs.den, self.den * rhs.num)
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceError {
+    Parse(ParseIntError),
+    Empty,
+    /// The difference rows ran out before any of them was all zeros,
+    /// so there's no polynomial the data pins down.
+    NotPolynomial,
+    /// The extrapolated value doesn't fit in an i128.
+    Overflow,
+}
+
+/// The polynomial through a sequence, with the first value at index 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polynomial {
+    /// The first entry of the sequence and of each difference row after it,
+    /// up to the last row that isn't all zeros.
+    /// The value at index n is the sum of diffs[k] * C(n, k).
+    diffs: Vec<i128>,
+}
+
+impl Polynomial {
+    pub fn fit(values: &[i64]) -> Result<Polynomial, SequenceError> {
+        if values.is_empty() {
+            return Err(SequenceError::Empty);
+        }
+
+        let rows = difference_rows(values);
+        match rows.iter().position(|row| row.iter().all(|x| *x == 0)) {
+            // an all-zero first row is the zero polynomial, keep one term for it
+            Some(0) => Ok(Polynomial { diffs: vec![0] }),
+            Some(zero_row) => Ok(Polynomial {
+                diffs: rows[..zero_row].iter().map(|row| row[0]).collect(),
+            }),
+            None => Err(SequenceError::NotPolynomial),
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.diffs.len() - 1
+    }
+
+    /// The value at any index, before, inside or after the data.
+    /// None if it doesn't fit in an i128.
+    pub fn eval(&self, n: i128) -> Option<i128> {
+        let mut total: i128 = 0;
+        // C(n, k), which is still a whole number for negative n
+        let mut binomial: i128 = 1;
+        for (k, d) in self.diffs.iter().enumerate() {
+            total = total.checked_add(d.checked_mul(binomial)?)?;
+            // C(n, k + 1) = C(n, k) * (n - k) / (k + 1), and the division is exact.
+            // The last one isn't needed, and working it out could overflow.
+            if k + 1 < self.diffs.len() {
+                let factor = n.checked_sub(k as i128)?;
+                binomial = binomial.checked_mul(factor)? / (k as i128 + 1);
+            }
+        }
+        Some(total)
+    }
+
+    /// Coefficients in the usual power basis, constant term first,
+    /// so the value at n is the sum of coefficients[j] * n^j.
+    pub fn coefficients(&self) -> Vec<Rational> {
+        let mut coefficients = vec![Rational::integer(0); self.diffs.len()];
+        // n (n - 1) ... (n - k + 1) / k!, which is C(n, k) as a polynomial in n
+        let mut binomial = vec![Rational::integer(1)];
+        for (k, d) in self.diffs.iter().enumerate() {
+            for (c, b) in coefficients.iter_mut().zip(&binomial) {
+                *c = *c + Rational::integer(*d) * *b;
+            }
+
+            // multiply by (n - k) / (k + 1)
+            let scale = Rational::new(1, k as i128 + 1);
+            let mut next = vec![Rational::integer(0); binomial.len() + 1];
+            for (j, b) in binomial.iter().enumerate() {
+                next[j + 1] = next[j + 1] + *b * scale;
+                next[j] = next[j] - *b * Rational::integer(k as i128) * scale;
+            }
+            binomial = next;
+        }
+        coefficients
+    }
+}
+
+/// The sequence followed by each row of differences,
+/// down to the single entry at the bottom of the pyramid.
+fn difference_rows(values: &[i64]) -> Vec<Vec<i128>> {
+    let first = values.iter().map(|v| *v as i128).collect::<Vec<_>>();
+    successors(Some(first), |prev| {
+        (prev.len() > 1).then(|| prev.windows(2).map(|w| w[1] - w[0]).collect())
+    })
+    .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Classification {
+    Polynomial(Polynomial),
+    /// Each value is the one before it times the ratio.
+    Geometric(Rational),
+    /// The difference rows never reach all zeros, so the data doesn't pin down
+    /// a polynomial. The best we could do was the polynomial of this degree
+    /// through the first degree + 1 values, and the residual is the furthest
+    /// it lands from any of the rest. A single value leaves nothing to check,
+    /// so it comes out as degree 0 with a residual of 0.
+    Neither {
+        degree: usize,
+        residual: i128,
+    },
+}
+
+/// Works out what kind of sequence this is.
+/// Anything polynomial counts as polynomial, even if it's also geometric
+/// (like a constant sequence).
+pub fn classify(values: &[i64]) -> Result<Classification, SequenceError> {
+    match Polynomial::fit(values) {
+        Ok(p) => return Ok(Classification::Polynomial(p)),
+        Err(SequenceError::NotPolynomial) => (),
+        Err(e) => return Err(e),
+    }
+
+    if let Some(ratio) = geometric_ratio(values) {
+        return Ok(Classification::Geometric(ratio));
+    }
+
+    let rows = difference_rows(values);
+    let (degree, residual) = (0..rows.len().saturating_sub(1).max(1))
+        .map(|degree| {
+            let p = Polynomial {
+                diffs: rows[..=degree].iter().map(|row| row[0]).collect(),
+            };
+            let residual = values
+                .iter()
+                .enumerate()
+                .skip(degree + 1)
+                .map(|(i, v)| {
+                    p.eval(i as i128)
+                        .and_then(|e| e.checked_sub(*v as i128))
+                        .map_or(i128::MAX, |d| d.saturating_abs())
+                })
+                .max()
+                .unwrap_or(0);
+            (degree, residual)
+        })
+        .min_by_key(|(_, residual)| *residual)
+        .unwrap();
+
+    Ok(Classification::Neither { degree, residual })
+}
+
+/// The common ratio, if there are at least three values
+/// and each is a fixed (non-zero) multiple of the one before.
+fn geometric_ratio(values: &[i64]) -> Option<Rational> {
+    if values.len() < 3 || values.contains(&0) {
+        return None;
+    }
+
+    let (a, b) = (values[0] as i128, values[1] as i128);
+    values
+        .windows(2)
+        .all(|w| w[1] as i128 * a == w[0] as i128 * b)
+        .then(|| Rational::new(b, a))
+}
+
+/// Extrapolates the sequence to index n, with the first value at index 0.
+pub fn extrapolate(values: &[i64], n: i128) -> Result<i128, SequenceError> {
+    Polynomial::fit(values)?
+        .eval(n)
+        .ok_or(SequenceError::Overflow)
+}
+
+/// Adds up the answers for each line,
+/// or lists every line (counting from 1) that didn't have one.
+pub fn sum_results<I>(results: I) -> Result<i128, Vec<(usize, SequenceError)>>
+where
+    I: IntoIterator<Item = Result<i128, SequenceError>>,
+{
+    let mut total: i128 = 0;
+    let mut errors = vec![];
+    for (i, r) in results.into_iter().enumerate() {
+        match r.and_then(|v| total.checked_add(v).ok_or(SequenceError::Overflow)) {
+            Ok(t) => total = t,
+            Err(e) => errors.push((i + 1, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(total)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses one line of space separated numbers.
+pub fn parse_line(line: &str) -> Result<Vec<i64>, SequenceError> {
+    line.split_ascii_whitespace()
+        .map(|x| x.parse::<i64>().map_err(SequenceError::Parse))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval() {
+        let input_str = "\
+0 3 6 9 12 15
+1 3 6 10 15 21
+10 13 16 21 30 45
+";
+        let polys = input_str
+            .lines()
+            .map(|l| Polynomial::fit(&parse_line(l).unwrap()).unwrap())
+            .collect::<Vec<_>>();
+
+        let next = polys.iter().map(|p| p.eval(6).unwrap()).collect::<Vec<_>>();
+        assert_eq!(next, vec![18, 28, 68]);
+        let prev = polys
+            .iter()
+            .map(|p| p.eval(-1).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(prev, vec![-3, 0, 5]);
+
+        // the triangular numbers (n + 1)(n + 2) / 2
+        assert_eq!(polys[1].eval(1000), Some(501501));
+        assert_eq!(polys[1].eval(-5), Some(6));
+        assert_eq!(polys[1].eval(i128::MAX / 2), None);
+    }
+
+    #[test]
+    fn test_eval_near_overflow() {
+        // n + 1: fits right up to i128::MAX - 1,
+        // even though the unused C(n, 2) would overflow long before that
+        let line = Polynomial::fit(&[1, 2, 3]).unwrap();
+        assert_eq!(line.eval(i128::MAX - 1), Some(i128::MAX));
+        assert_eq!(line.eval(i128::MAX), None);
+        assert_eq!(line.eval(i128::MIN), Some(i128::MIN + 1));
+
+        // a constant never needs C(n, 1) at all
+        let constant = Polynomial::fit(&[7, 7]).unwrap();
+        assert_eq!(constant.eval(i128::MIN), Some(7));
+
+        // (n + 1)(n + 2) / 2 fits easily at n = 2^63,
+        // but C(n, 3) would not
+        let triangular = Polynomial::fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        let n = 1 << 63;
+        assert_eq!(triangular.eval(n), Some((n + 1) * (n + 2) / 2));
+    }
+
+    #[test]
+    fn test_degree_and_coefficients() {
+        let input_str = "\
+0 3 6 9 12 15
+1 3 6 10 15 21
+10 13 16 21 30 45
+";
+        let polys = input_str
+            .lines()
+            .map(|l| Polynomial::fit(&parse_line(l).unwrap()).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            polys.iter().map(|p| p.degree()).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            polys[0].coefficients(),
+            vec![Rational::integer(0), Rational::integer(3)]
+        );
+        assert_eq!(
+            polys[1].coefficients(),
+            vec![
+                Rational::integer(1),
+                Rational::new(3, 2),
+                Rational::new(1, 2)
+            ]
+        );
+
+        for p in &polys {
+            let coefficients = p.coefficients();
+            for n in -10..20 {
+                let value = coefficients
+                    .iter()
+                    .rev()
+                    .fold(Rational::integer(0), |acc, c| {
+                        acc * Rational::integer(n) + *c
+                    });
+                assert_eq!(value, Rational::integer(p.eval(n).unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_fit_errors() {
+        assert_eq!(Polynomial::fit(&[]), Err(SequenceError::Empty));
+        assert_eq!(
+            Polynomial::fit(&[1, 2, 4, 8]),
+            Err(SequenceError::NotPolynomial)
+        );
+        assert_eq!(Polynomial::fit(&[5]), Err(SequenceError::NotPolynomial));
+        assert_eq!(Polynomial::fit(&[0, 0]).unwrap().degree(), 0);
+        assert_eq!(Polynomial::fit(&[7, 7]).unwrap().eval(-100), Some(7));
+    }
+
+    #[test]
+    fn test_rational() {
+        assert_eq!(Rational::new(2, -4), Rational::new(-1, 2));
+        assert_eq!(Rational::new(0, -3), Rational::integer(0));
+        assert_eq!(
+            Rational::new(1, 2) + Rational::new(1, 3),
+            Rational::new(5, 6)
+        );
+        assert_eq!(
+            Rational::new(1, 2) / Rational::new(-1, 4),
+            Rational::integer(-2)
+        );
+        assert_eq!(Rational::new(3, 6).to_string(), "1/2");
+        assert_eq!(Rational::integer(-4).to_string(), "-4");
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(&[0, 3, 6, 9, 12, 15]),
+            Ok(Classification::Polynomial(
+                Polynomial::fit(&[0, 3, 6, 9, 12, 15]).unwrap()
+            ))
+        );
+        assert_eq!(
+            classify(&[4, 4, 4]),
+            Ok(Classification::Polynomial(Polynomial { diffs: vec![4] }))
+        );
+        assert_eq!(
+            classify(&[1, 2, 4, 8, 16]),
+            Ok(Classification::Geometric(Rational::integer(2)))
+        );
+        assert_eq!(
+            classify(&[-8, 12, -18, 27]),
+            Ok(Classification::Geometric(Rational::new(-3, 2)))
+        );
+        // straight line apart from the last value
+        assert_eq!(
+            classify(&[1, 2, 3, 4, 5, 9]),
+            Ok(Classification::Neither {
+                degree: 1,
+                residual: 3
+            })
+        );
+        // one value is too few to confirm even a constant
+        assert_eq!(
+            classify(&[5]),
+            Ok(Classification::Neither {
+                degree: 0,
+                residual: 0
+            })
+        );
+        assert_eq!(classify(&[]), Err(SequenceError::Empty));
+    }
+
+    #[test]
+    fn test_sum_results() {
+        let results = "0 3 6 9 12 15\n1 2 4 8\n1 x 3\n\n1 3 6 10 15 21\n"
+            .lines()
+            .map(|l| parse_line(l).and_then(|t| extrapolate(&t, t.len() as i128)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(results[0], Ok(18));
+        assert_eq!(results[4], Ok(28));
+
+        let errors = sum_results(results).unwrap_err();
+        assert_eq!(
+            errors.iter().map(|(line, _)| *line).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        assert_eq!(errors[0].1, SequenceError::NotPolynomial);
+        assert!(matches!(errors[1].1, SequenceError::Parse(_)));
+        assert_eq!(errors[2].1, SequenceError::Empty);
+
+        assert_eq!(sum_results([Ok(18), Ok(28)]), Ok(46));
+        assert_eq!(
+            extrapolate(&[0, 3, 6], i128::MAX),
+            Err(SequenceError::Overflow)
+        );
+    }
+}
@@ -0,0 +1,6 @@
+pub mod day11;
+pub mod day16;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;